@@ -2,7 +2,7 @@ use anyhow::Result;
 use perovskite::{
     menu::{MenuItemExt, MenuItemWithAction},
     muda::{AboutMetadata, Menu, MenuItem, PredefinedMenuItem, Submenu},
-    App, AppSettings, RenderContext,
+    App, AppSettings, Color, RenderContext,
 };
 
 const WIDTH: u32 = 320;
@@ -72,29 +72,19 @@ impl App for World {
     /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
     fn draw(&mut self, ctx: &mut RenderContext) -> Result<()> {
         {
-            let frame = ctx.pixels_mut().frame_mut();
-
-            for (i, cur_pixel) in frame.chunks_exact_mut(4).enumerate() {
-                let x = (i % WIDTH as usize) as i16;
-                let y = (i / WIDTH as usize) as i16;
-
-                let inside_the_box = x >= self.box_x
-                    && x < self.box_x + BOX_SIZE
-                    && y >= self.box_y
-                    && y < self.box_y + BOX_SIZE;
-
-                let rgba = if inside_the_box {
-                    [0x5e, 0x48, 0xe8, 0xff]
-                } else {
-                    [0x48, 0xb2, 0xe8, 0xff]
-                };
-
-                cur_pixel.copy_from_slice(&rgba);
-            }
+            let mut canvas = ctx.canvas();
+
+            canvas.clear(Color::hex(0x48b2e8));
+            canvas.fill_rect(
+                self.box_x as i32,
+                self.box_y as i32,
+                BOX_SIZE as i32,
+                BOX_SIZE as i32,
+                Color::hex(0x5e48e8),
+            );
         }
 
-        ctx.window().pre_present_notify();
-        ctx.pixels_mut().render()?;
+        ctx.present()?;
 
         Ok(())
     }
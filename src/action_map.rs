@@ -0,0 +1,582 @@
+//! Named input actions and axes, bound to keys and mouse buttons that players can rebind.
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::{
+    key_names::{key_name, mouse_button_name, parse_key, parse_mouse_button},
+    InputManager, KeyboardModifiers,
+};
+
+/// The header on the first line of every bindings file.
+const HEADER: &str = "perovskite-bindings";
+
+/// The version of the bindings format written by this version of perovskite.
+pub const BINDINGS_VERSION: u32 = 1;
+
+/// A key or mouse button that triggers a binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// The modifiers that must be held for a binding to trigger.
+/// Either the left or right key counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChordModifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The "windows" key on PC and "command" key on Mac.
+    pub super_key: bool,
+}
+
+impl ChordModifiers {
+    fn names(&self) -> [(&'static str, bool); 4] {
+        [
+            ("Shift", self.shift),
+            ("Control", self.control),
+            ("Alt", self.alt),
+            ("Super", self.super_key),
+        ]
+    }
+
+    fn count(&self) -> usize {
+        self.names()
+            .iter()
+            .filter(|(_, required)| *required)
+            .count()
+    }
+
+    /// Returns true if every modifier required here is also required by `other`.
+    fn is_subset_of(&self, other: &ChordModifiers) -> bool {
+        self.names()
+            .iter()
+            .zip(other.names())
+            .all(|((_, required), (_, other_required))| !required || other_required)
+    }
+
+    /// Returns true if every required modifier is held.
+    fn is_held(&self, modifiers: &KeyboardModifiers) -> bool {
+        (!self.shift || modifiers.left_shift || modifiers.right_shift)
+            && (!self.control || modifiers.left_control || modifiers.right_control)
+            && (!self.alt || modifiers.left_alt || modifiers.right_alt)
+            && (!self.super_key || modifiers.left_super || modifiers.right_super)
+    }
+}
+
+/// A key or mouse button, and any modifiers that must be held with it, e.g. Control+S.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub modifiers: ChordModifiers,
+}
+
+impl Binding {
+    /// Bind a key, with no modifiers.
+    pub fn key(key_code: KeyCode) -> Self {
+        Self {
+            trigger: Trigger::Key(key_code),
+            modifiers: ChordModifiers::default(),
+        }
+    }
+
+    /// Bind a mouse button, with no modifiers.
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            trigger: Trigger::Mouse(button),
+            modifiers: ChordModifiers::default(),
+        }
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+
+    pub fn with_control(mut self) -> Self {
+        self.modifiers.control = true;
+        self
+    }
+
+    pub fn with_alt(mut self) -> Self {
+        self.modifiers.alt = true;
+        self
+    }
+
+    pub fn with_super(mut self) -> Self {
+        self.modifiers.super_key = true;
+        self
+    }
+
+    /// Parse a binding from its config file form, e.g. `Space`, `Control+KeyS` or `MouseLeft`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        let trigger = parts.pop().unwrap_or_default();
+
+        let trigger = if let Some(key_code) = parse_key(trigger) {
+            Trigger::Key(key_code)
+        } else if let Some(button) = trigger.strip_prefix("Mouse").and_then(parse_mouse_button) {
+            Trigger::Mouse(button)
+        } else {
+            bail!("Unknown key or mouse button {:?}", trigger);
+        };
+
+        let mut modifiers = ChordModifiers::default();
+        for name in parts {
+            let required = match name {
+                "Shift" => &mut modifiers.shift,
+                "Control" => &mut modifiers.control,
+                "Alt" => &mut modifiers.alt,
+                "Super" => &mut modifiers.super_key,
+                _ => bail!("Unknown modifier {:?}", name),
+            };
+            *required = true;
+        }
+
+        Ok(Self { trigger, modifiers })
+    }
+
+    /// Format the binding in its config file form.
+    /// Returns `None` for keys without a name, which can't be read back.
+    pub fn name(&self) -> Option<String> {
+        let mut text = String::new();
+        for (name, required) in self.modifiers.names() {
+            if required {
+                text.push_str(name);
+                text.push('+');
+            }
+        }
+
+        match self.trigger {
+            Trigger::Key(key_code) => text.push_str(key_name(key_code)?),
+            Trigger::Mouse(button) => {
+                text.push_str("Mouse");
+                text.push_str(&mouse_button_name(button));
+            }
+        }
+
+        Some(text)
+    }
+}
+
+/// A pair of bindings that drive an axis, towards -1 and +1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxisBinding {
+    pub negative: Binding,
+    pub positive: Binding,
+}
+
+/// Named actions, such as "jump", and axes, such as "move_x", bound to keys, mouse buttons and
+/// modifier chords. Set it with `InputManager::set_actions`, then query actions by name with
+/// `InputManager::action_pressed` or `InputManager::axis`.
+///
+/// When a key is bound both on its own and as part of a chord, holding the chord's modifiers
+/// only triggers the chord. So binding `KeyS` to "move_down" and `Control+KeyS` to "save" works
+/// as expected.
+///
+/// Bindings can be saved to a plain text file, so players can rebind controls:
+///
+/// ```text
+/// perovskite-bindings 1
+/// action jump Space
+/// action jump MouseLeft
+/// action save Control+KeyS
+/// axis move_x KeyA KeyD
+/// axis move_x ArrowLeft ArrowRight
+/// ```
+///
+/// The first line names the format and its version. Each `action` line binds an action to a key
+/// or mouse button, and each `axis` line binds an axis to a negative and a positive key or mouse
+/// button. Keys are named after winit's `KeyCode` variants, and mouse buttons are `Mouse`
+/// followed by `Left`, `Right`, `Middle`, `Back`, `Forward` or a button number. Modifiers are
+/// written before the key, from `Shift`, `Control`, `Alt` and `Super`, joined with `+`. Names
+/// can't be empty, contain whitespace or start with `#`.
+///
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    /// Create an action map with no bindings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind an action to a key, mouse button or chord, in addition to its existing bindings.
+    /// Returns an error if the name can't be saved, see `ActionMap`.
+    pub fn bind(&mut self, action: &str, binding: Binding) -> Result<()> {
+        validate_name(action)?;
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    pub fn with_binding(mut self, action: &str, binding: Binding) -> Result<Self> {
+        self.bind(action, binding)?;
+        Ok(self)
+    }
+
+    /// Bind an axis to a pair of inputs, in addition to its existing bindings.
+    /// Returns an error if the name can't be saved, see `ActionMap`.
+    pub fn bind_axis(&mut self, axis: &str, negative: Binding, positive: Binding) -> Result<()> {
+        validate_name(axis)?;
+        let binding = AxisBinding { negative, positive };
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    pub fn with_axis(mut self, axis: &str, negative: Binding, positive: Binding) -> Result<Self> {
+        self.bind_axis(axis, negative, positive)?;
+        Ok(self)
+    }
+
+    /// Replace all of an action's bindings, e.g. when a player rebinds it.
+    pub fn set_bindings(&mut self, action: &str, bindings: Vec<Binding>) -> Result<()> {
+        validate_name(action)?;
+        self.actions.insert(action.to_string(), bindings);
+        Ok(())
+    }
+
+    /// Replace all of an axis's bindings.
+    pub fn set_axis_bindings(&mut self, axis: &str, bindings: Vec<AxisBinding>) -> Result<()> {
+        validate_name(axis)?;
+        self.axes.insert(axis.to_string(), bindings);
+        Ok(())
+    }
+
+    /// Remove every binding for an action or axis.
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    /// Get the bindings for an action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get the bindings for an axis.
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get the names of all actions, in alphabetical order.
+    pub fn actions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.actions.keys().map(String::as_str)
+    }
+
+    /// Get the names of all axes, in alphabetical order.
+    pub fn axes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.axes.keys().map(String::as_str)
+    }
+
+    /// Load bindings from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bindings {}", path.display()))?;
+
+        Self::parse(&text).with_context(|| format!("Failed to load bindings {}", path.display()))
+    }
+
+    /// Save the bindings to a file.
+    /// Fails if a binding is to a key without a name, as it couldn't be read back.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some((name, binding)) = self.find_unnamed(Binding::name) {
+            bail!(
+                "Failed to write bindings {}: the key {:?} bound to {} has no name",
+                path.display(),
+                binding.trigger,
+                name
+            );
+        }
+
+        std::fs::write(path, self.to_string())
+            .with_context(|| format!("Failed to write bindings {}", path.display()))
+    }
+
+    /// Parse bindings from their text format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (_, header) = lines.next().ok_or_else(|| anyhow!("Bindings are empty"))?;
+        let version = header
+            .strip_prefix(HEADER)
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| anyhow!("Not a bindings file"))?;
+        ensure!(
+            version == BINDINGS_VERSION,
+            "Unsupported bindings version {}, expected {}",
+            version,
+            BINDINGS_VERSION
+        );
+
+        let mut map = Self::new();
+        for (number, line) in lines {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let binding = |index: usize| {
+                let text = parts.get(index).copied().unwrap_or_default();
+                Binding::parse(text).with_context(|| format!("Line {}", number))
+            };
+
+            let result = match parts.as_slice() {
+                ["action", action, _] => map.bind(action, binding(2)?),
+                ["axis", axis, _, _] => map.bind_axis(axis, binding(2)?, binding(3)?),
+                _ => bail!("Line {}: expected an action or axis binding", number),
+            };
+            result.with_context(|| format!("Line {}", number))?;
+        }
+
+        Ok(map)
+    }
+
+    /// Find a binding that `name` can't format, and the action or axis it belongs to.
+    fn find_unnamed(&self, name: impl Fn(&Binding) -> Option<String>) -> Option<(&str, Binding)> {
+        let actions = self.actions.iter().flat_map(|(action, bindings)| {
+            bindings
+                .iter()
+                .map(move |binding| (action.as_str(), *binding))
+        });
+        let axes = self.axes.iter().flat_map(|(axis, bindings)| {
+            bindings.iter().flat_map(move |binding| {
+                [
+                    (axis.as_str(), binding.negative),
+                    (axis.as_str(), binding.positive),
+                ]
+            })
+        });
+
+        actions
+            .chain(axes)
+            .find(|(_, binding)| name(binding).is_none())
+    }
+
+    /// Returns true if any active binding for the action matches the predicate.
+    pub(crate) fn any_binding(
+        &self,
+        action: &str,
+        input: &InputManager,
+        mut predicate: impl FnMut(Trigger) -> bool,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| self.is_active(binding, input) && predicate(binding.trigger))
+    }
+
+    /// Get the value of an axis, from -1 to 1.
+    pub(crate) fn axis_value(&self, axis: &str, input: &InputManager) -> f32 {
+        let down = |binding: &Binding| {
+            self.is_active(binding, input) && trigger_down(input, binding.trigger)
+        };
+
+        let value: f32 = self
+            .axis_bindings(axis)
+            .iter()
+            .map(|axis| down(&axis.positive) as i32 as f32 - down(&axis.negative) as i32 as f32)
+            .sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    /// Returns true if the binding's modifiers are held, and no chord on the same trigger with
+    /// more modifiers is held.
+    fn is_active(&self, binding: &Binding, input: &InputManager) -> bool {
+        let modifiers = input.modifiers();
+        if !binding.modifiers.is_held(&modifiers) {
+            return false;
+        }
+
+        let count = binding.modifiers.count();
+        let axis_bindings = self
+            .axes
+            .values()
+            .flatten()
+            .flat_map(|axis| [axis.negative, axis.positive]);
+        let shadowed = self
+            .actions
+            .values()
+            .flatten()
+            .copied()
+            .chain(axis_bindings)
+            .any(|other| {
+                other.trigger == binding.trigger
+                    && other.modifiers.count() > count
+                    && binding.modifiers.is_subset_of(&other.modifiers)
+                    && other.modifiers.is_held(&modifiers)
+            });
+
+        !shadowed
+    }
+}
+
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, BINDINGS_VERSION)?;
+
+        // Keys without a name can't be read back, so are left out, and `save` fails instead
+        for (action, bindings) in &self.actions {
+            for binding in bindings.iter().filter_map(Binding::name) {
+                writeln!(f, "action {} {}", action, binding)?;
+            }
+        }
+
+        for (axis, bindings) in &self.axes {
+            for binding in bindings {
+                if let (Some(negative), Some(positive)) =
+                    (binding.negative.name(), binding.positive.name())
+                {
+                    writeln!(f, "axis {} {} {}", axis, negative, positive)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that an action or axis name can be written to a bindings file and read back.
+fn validate_name(name: &str) -> Result<()> {
+    ensure!(!name.is_empty(), "Action and axis names can't be empty");
+    ensure!(
+        !name.contains(char::is_whitespace) && !name.starts_with('#'),
+        "Invalid name {:?}, names can't contain whitespace or start with #",
+        name
+    );
+    Ok(())
+}
+
+pub(crate) fn trigger_pressed(input: &InputManager, trigger: Trigger) -> bool {
+    match trigger {
+        Trigger::Key(key_code) => input.key_pressed(key_code),
+        Trigger::Mouse(button) => input.mouse_pressed(button),
+    }
+}
+
+pub(crate) fn trigger_down(input: &InputManager, trigger: Trigger) -> bool {
+    match trigger {
+        Trigger::Key(key_code) => input.key_down(key_code),
+        Trigger::Mouse(button) => input.mouse_down(button),
+    }
+}
+
+pub(crate) fn trigger_released(input: &InputManager, trigger: Trigger) -> bool {
+    match trigger {
+        Trigger::Key(key_code) => input.key_released(key_code),
+        Trigger::Mouse(button) => input.mouse_released(button),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let map = ActionMap::new()
+            .with_binding("jump", Binding::key(KeyCode::Space))
+            .unwrap()
+            .with_binding("jump", Binding::mouse(MouseButton::Left))
+            .unwrap()
+            .with_binding("save", Binding::key(KeyCode::KeyS).with_control())
+            .unwrap()
+            .with_binding(
+                "screenshot",
+                Binding::mouse(MouseButton::Other(7))
+                    .with_shift()
+                    .with_alt()
+                    .with_super(),
+            )
+            .unwrap()
+            .with_axis(
+                "move_x",
+                Binding::key(KeyCode::KeyA),
+                Binding::key(KeyCode::KeyD),
+            )
+            .unwrap()
+            .with_axis(
+                "move_x",
+                Binding::mouse(MouseButton::Back),
+                Binding::mouse(MouseButton::Forward).with_control(),
+            )
+            .unwrap();
+
+        let text = map.to_string();
+        assert_eq!(ActionMap::parse(&text).unwrap(), map);
+        assert!(text.contains("action save Control+KeyS\n"), "{}", text);
+        assert!(
+            text.contains("action screenshot Shift+Alt+Super+Mouse7\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_cant_be_saved() {
+        let mut map = ActionMap::new();
+        let binding = Binding::key(KeyCode::Space);
+
+        assert!(map.bind("", binding).is_err());
+        assert!(map.bind("jump high", binding).is_err());
+        assert!(map.bind("#jump", binding).is_err());
+        assert!(map.bind_axis("move\tx", binding, binding).is_err());
+        assert!(map.set_bindings("", vec![binding]).is_err());
+        assert!(map.set_axis_bindings("#move_x", Vec::new()).is_err());
+        assert_eq!(map, ActionMap::new());
+
+        assert!(ActionMap::parse("perovskite-bindings 1\naction #jump Space").is_err());
+    }
+
+    #[test]
+    fn parse_reports_the_line() {
+        let error = ActionMap::parse("perovskite-bindings 1\n\naction jump NotAKey").unwrap_err();
+        assert_eq!(error.to_string(), "Line 3");
+    }
+
+    #[test]
+    fn save_fails_on_keys_without_a_name() {
+        let map = ActionMap::new()
+            .with_binding("jump", Binding::key(KeyCode::Space))
+            .unwrap()
+            .with_axis(
+                "move_x",
+                Binding::key(KeyCode::KeyA),
+                Binding::key(KeyCode::KeyD),
+            )
+            .unwrap();
+        assert_eq!(map.find_unnamed(Binding::name), None);
+
+        // Every key winit has today is named, so stand in for one added later
+        let unnamed = |binding: &Binding| {
+            (binding.trigger != Trigger::Key(KeyCode::KeyD))
+                .then(|| binding.name())
+                .flatten()
+        };
+        assert_eq!(
+            map.find_unnamed(unnamed),
+            Some(("move_x", Binding::key(KeyCode::KeyD)))
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("perovskite-bindings-{}.txt", std::process::id()));
+        map.save(&path).unwrap();
+        let loaded = ActionMap::load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), map);
+    }
+}
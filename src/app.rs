@@ -1,16 +1,25 @@
 use anyhow::{Context, Result};
 use muda::MenuEvent;
-use std::time::{Duration, Instant};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
 use winit::{
-    dpi::LogicalSize,
+    dpi::{LogicalSize, PhysicalSize},
     event::{ElementState, Event, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopWindowTarget},
     window::{Window, WindowBuilder},
 };
 
 use crate::{
+    crash::{install_panic_hook, CrashReport},
+    error::handle_error,
+    frame_loop::{FrameLoop, FrameStatus},
+    headless::run_headless,
     menu::{init_menu, init_menu_hooks, show_context_menu_for_window},
-    AppSettings, RenderContext,
+    recording::{finish_session, start_session},
+    AppError, AppSettings, ErrorAction, ErrorPhase, HeadlessConfig, HeadlessRun, RedrawMode,
+    RenderContext, TimerId,
 };
 
 /// A trait for creating an application, utilising a fixed timestep.
@@ -27,7 +36,9 @@ pub trait App: Sized {
     /// Is called in a loop, before `draw`.
     /// It is guaranteed to be called at least once per minimum frame time,
     /// but may be called more than once per frame before `draw` is called.
-    /// The delta time is guaranteed to not exceed the maximum frame time.
+    /// The delta time is guaranteed to not exceed the maximum frame time, multiplied by the time
+    /// scale.
+    /// How often it is called, and with what delta time, depends on the `TimestepMode`.
     fn update(&mut self, ctx: &mut RenderContext) -> Result<()>;
 
     /// Render the app.
@@ -41,6 +52,76 @@ pub trait App: Sized {
         Ok(())
     }
 
+    #[allow(unused_variables)]
+    /// Called when simulation time has been thrown away, before `draw`.
+    /// This happens when a frame takes longer than the maximum frame time, or needs more than the
+    /// maximum number of updates. Use it to resync state that depends on real time, such as
+    /// networked games.
+    fn on_lag(&mut self, lost: Duration) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called after the window has been resized, with its new size in physical pixels.
+    /// The frame buffer keeps its size, and is scaled to fit the window.
+    fn on_resize(&mut self, ctx: &mut RenderContext, new_size: PhysicalSize<u32>) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when the window gains or loses keyboard focus.
+    fn on_focus_changed(&mut self, ctx: &mut RenderContext, focused: bool) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when the window is minimized, and again with `false` when it is restored.
+    fn on_minimized(&mut self, ctx: &mut RenderContext, minimized: bool) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when the user tries to close the window.
+    /// Return false to keep the window open, e.g. to ask about unsaved changes first.
+    fn on_close_requested(&mut self, ctx: &mut RenderContext) -> bool {
+        true
+    }
+
+    #[allow(unused_variables)]
+    /// Called when the app is suspended by the OS, e.g. when sent to the background on mobile.
+    fn on_suspend(&mut self, ctx: &mut RenderContext) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when the app is resumed after being suspended.
+    fn on_resume(&mut self, ctx: &mut RenderContext) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when a timer scheduled with `RenderContext::timers` fires.
+    /// Is called just before the `update` the timer fired in.
+    fn on_timer(&mut self, ctx: &mut RenderContext, id: TimerId) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called once when the app is exiting.
+    /// Is called after `RenderContext::exit` has been requested, or the window has been closed.
+    /// Use this function to save any state, or clean up any resources, before the event loop ends.
+    fn on_exit(&mut self, ctx: &mut RenderContext) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// Called when any other function of the app returns an error, to decide what happens next.
+    /// By default, the error is printed to stderr and the app exits.
+    fn on_error(&mut self, ctx: &mut RenderContext, error: &AppError) -> ErrorAction {
+        eprintln!("{}", error);
+        ErrorAction::Exit
+    }
+
     fn run(mut app: Self) -> Result<()> {
         let mut event_loop_builder = EventLoopBuilder::new();
 
@@ -63,112 +144,314 @@ pub trait App: Sized {
             settings.frame_height,
         )?;
 
-        let mut current_time = Instant::now();
-        let mut accumulated_time = Duration::ZERO;
-        let mut skip_update = false;
+        render_context.set_stats_overlay(settings.stats_overlay);
+        start_session(&settings, &mut render_context.input)?;
+
+        // State for on-demand redraws
+        let on_demand = settings.redraw_mode == RedrawMode::OnDemand;
+        let control_flow = if on_demand {
+            ControlFlow::Wait
+        } else {
+            ControlFlow::Poll
+        };
+
+        let mut state = LoopState {
+            current_time: Instant::now(),
+            frame_loop: FrameLoop::new(settings.timestep_mode, settings.max_updates_per_frame),
+            skip_update: false,
+            on_demand,
+            redraw_pending: true,
+            input_pending: false,
+            animating: false,
+            minimized: false,
+            suspended: false,
+        };
+
+        // Set once a panic has been caught, after which only the crash screen is shown
+        let mut crashed = false;
+        if settings.crash_report_dir.is_some() {
+            install_panic_hook();
+        }
 
-        event_loop.set_control_flow(ControlFlow::Poll);
+        event_loop.set_control_flow(control_flow);
         event_loop.run(move |event, event_loop| {
-            event_loop.set_control_flow(ControlFlow::Poll);
+            // Save any input recording however the app exits, so errors can be replayed
+            if let Event::LoopExiting = event {
+                let result = finish_session(&settings, &mut render_context.input);
+                if crashed {
+                    if let Err(error) = result {
+                        eprintln!("{}", error);
+                    }
+                } else {
+                    let _ = handle_error(&mut app, &mut render_context, ErrorPhase::Exit, result);
+                }
+                return;
+            }
 
-            // Let the app handle the event
-            if handle_error(app.handle_event(&event), event_loop).is_err() {
+            // The app has already exited, so ignore any events still being delivered
+            if event_loop.exiting() {
                 return;
             }
 
-            // Handle menu events
-            let menu_channel = MenuEvent::receiver();
-            if let Ok(event) = menu_channel.try_recv() {
-                if let Some(dispatch) = settings.menu_dispatch_map.get(&event.id) {
-                    dispatch();
+            if crashed {
+                handle_crashed_event(&event, &mut render_context, event_loop);
+                return;
+            }
+
+            event_loop.set_control_flow(control_flow);
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                handle_app_event(
+                    &mut app,
+                    &mut render_context,
+                    &settings,
+                    &mut state,
+                    event,
+                    event_loop,
+                )
+            }));
+
+            if let Err(payload) = result {
+                let Some(dir) = settings.crash_report_dir.as_ref() else {
+                    panic::resume_unwind(payload);
+                };
+
+                let report =
+                    CrashReport::new(&*payload, settings.app_version.as_deref(), &render_context);
+                let report_path = report.save(dir, &render_context);
+                match &report_path {
+                    Ok(path) => eprintln!("Crash report saved to {}", path.display()),
+                    Err(error) => eprintln!("{}", error),
+                }
+
+                // The app may be in a broken state, so it isn't called again
+                crashed = true;
+
+                if !settings.crash_screen {
+                    event_loop.exit();
+                    return;
+                }
+
+                render_context.set_stats_overlay(false);
+                report.draw_screen(&mut render_context.canvas(), &report_path);
+                event_loop.set_control_flow(ControlFlow::Wait);
+                if let Some(window) = render_context.try_window() {
+                    window.request_redraw();
                 }
             }
+        })?;
 
-            // Process any input events
-            render_context.input.handle_event(&event);
-
-            match event {
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::CloseRequested => event_loop.exit(),
-
-                    WindowEvent::MouseInput {
-                        state: ElementState::Pressed,
-                        button: MouseButton::Right,
-                        ..
-                    } => {
-                        if let Some(context_menu) = settings.context_menu.as_ref() {
-                            show_context_menu_for_window(render_context.window(), context_menu);
-                        }
-                    }
+        Ok(())
+    }
 
-                    WindowEvent::Resized(size) => {
-                        let pixels = render_context.pixels_mut();
-
-                        let resize_result = pixels
-                            .resize_surface(size.width, size.height)
-                            .with_context(|| {
-                                format!(
-                                    "Failed to resize pixels surface to {}x{}",
-                                    size.width, size.height
-                                )
-                            });
-
-                        if handle_error(resize_result, event_loop).is_err() {
-                            #[allow(clippy::needless_return)]
-                            return;
-                        }
-
-                        // Skip the next update, as the redraw event will be sent immediately after this one
-                        skip_update = true;
-                    }
+    /// Run the app without a window, drawing to an in-memory frame buffer.
+    /// Time is simulated, so each frame advances by a fixed amount regardless of how long it takes.
+    /// Useful for tests and CI, where there is no display or GPU.
+    fn run_headless(app: Self, config: HeadlessConfig<Self>) -> Result<HeadlessRun<Self>> {
+        run_headless(app, config)
+    }
+}
 
-                    WindowEvent::RedrawRequested => {
-                        if !skip_update {
-                            let mut delta_time = current_time.elapsed();
-                            current_time = Instant::now();
+/// State carried between events by the event loop.
+struct LoopState {
+    current_time: Instant,
+    frame_loop: FrameLoop,
+    skip_update: bool,
+
+    // State for on-demand redraws. The loop sleeps in `AboutToWait` until a redraw is pending,
+    // or until the deadline of a redraw requested for later
+    on_demand: bool,
+    /// Set by input, menu items and due redraw requests, and cleared when a redraw is requested
+    /// from the window.
+    redraw_pending: bool,
+    /// Set when input arrives, and cleared once an update has run to see it. While set, the loop
+    /// wakes up again when the next update is owed, as a fixed timestep may not have run one yet.
+    input_pending: bool,
+    /// Set after a redraw if the app asked for another one or a timer is running, so the time
+    /// until the next redraw was spent running rather than idle.
+    animating: bool,
+
+    // State for lifecycle hooks
+    minimized: bool,
+    suspended: bool,
+}
 
-                            if delta_time > render_context.max_frame_time() {
-                                delta_time = render_context.max_frame_time();
-                            }
+/// Handle an event from the event loop while the app is running normally.
+fn handle_app_event<A: App>(
+    app: &mut A,
+    render_context: &mut RenderContext,
+    settings: &AppSettings,
+    state: &mut LoopState,
+    event: Event<()>,
+    event_loop: &EventLoopWindowTarget<()>,
+) {
+    // Let the app handle the event
+    let result = app.handle_event(&event);
+    if dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop) {
+        return;
+    }
 
-                            accumulated_time += delta_time;
+    // Handle menu events
+    let menu_channel = MenuEvent::receiver();
+    if let Ok(event) = menu_channel.try_recv() {
+        if let Some(dispatch) = settings.menu_dispatch_map.get(&event.id) {
+            dispatch();
+        }
+        state.redraw_pending = true;
+        state.input_pending = true;
+    }
 
-                            render_context.input.update();
+    // Process any input events
+    render_context.input.handle_event(&event);
+
+    // Any window event other than a redraw could be input the app needs to react to
+    if let Event::WindowEvent { event, .. } = &event {
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            state.redraw_pending = true;
+            state.input_pending = true;
+        }
+    }
 
-                            while accumulated_time >= render_context.target_frame_time() {
-                                render_context.delta_time = render_context.target_frame_time();
+    match event {
+        Event::WindowEvent { event, .. } => match event {
+            // The app can veto closing, e.g. to ask about unsaved changes
+            WindowEvent::CloseRequested if app.on_close_requested(render_context) => {
+                render_context.exit();
+                exit_app(app, render_context, event_loop);
+            }
 
-                                if handle_error(app.update(&mut render_context), event_loop)
-                                    .is_err()
-                                {
-                                    return;
-                                }
+            WindowEvent::Focused(focused) => {
+                let result = app.on_focus_changed(render_context, focused);
+                dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop);
+            }
 
-                                accumulated_time -= render_context.target_frame_time();
-                            }
-                        } else {
-                            skip_update = false;
-                        }
+            WindowEvent::Occluded(_) => {
+                let result = update_minimized(app, render_context, &mut state.minimized);
+                dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop);
+            }
 
-                        if handle_error(app.draw(&mut render_context), event_loop).is_err() {
-                            #[allow(clippy::needless_return)]
-                            return;
-                        }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => {
+                if let (Some(context_menu), Some(window)) =
+                    (settings.context_menu.as_ref(), render_context.try_window())
+                {
+                    show_context_menu_for_window(window, context_menu);
+                }
+            }
+
+            WindowEvent::Resized(size) => {
+                let result = update_minimized(app, render_context, &mut state.minimized);
+                if dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop) {
+                    return;
+                }
+
+                // A minimized window has no surface to resize
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+
+                let result = render_context
+                    .resize_surface(size.width, size.height)
+                    .and_then(|_| app.on_resize(render_context, size));
+                if dispatch_error(app, render_context, ErrorPhase::Resize, result, event_loop) {
+                    return;
+                }
+
+                // Skip the next update, as the redraw event will be sent immediately after this one
+                state.skip_update = true;
+            }
+
+            WindowEvent::RedrawRequested => {
+                let elapsed = if !state.skip_update {
+                    let delta_time = state.current_time.elapsed();
+                    state.current_time = Instant::now();
+
+                    Some(redraw_delta_time(
+                        delta_time,
+                        render_context.target_frame_time(),
+                        state.on_demand && !state.animating,
+                    ))
+                } else {
+                    state.skip_update = false;
+                    None
+                };
+
+                let updates_before = state.frame_loop.update_count();
+                let frame_result = state.frame_loop.run_frame(app, render_context, elapsed);
+
+                // The app has already been told about any error that ends it
+                if let Ok(FrameStatus::Exit) | Err(_) = frame_result {
+                    exit_app(app, render_context, event_loop);
+                    return;
+                }
+
+                if state.frame_loop.update_count() > updates_before {
+                    state.input_pending = false;
+                }
+
+                // Wake up when the next timer is due, keeping real time flowing until then
+                let mut timer_pending = false;
+                if state.on_demand {
+                    let wait = time_until_next_timer(render_context, &state.frame_loop);
+                    if let Some(deadline) =
+                        wait.and_then(|wait| state.current_time.checked_add(wait))
+                    {
+                        render_context.request_redraw_at(deadline);
+                        timer_pending = true;
                     }
+                }
+                state.animating = render_context.redraw_requested() || timer_pending;
+
+                // Input arrived before the next update was owed, so wake up when it is
+                if state.on_demand && state.input_pending {
+                    let wait = state
+                        .frame_loop
+                        .time_until_next_update(render_context.target_frame_time());
+                    render_context.request_redraw_at(state.current_time + wait);
+                }
+            }
 
-                    _ => {}
-                },
+            _ => {}
+        },
 
-                Event::AboutToWait => {
-                    render_context.window().request_redraw();
+        Event::Suspended => {
+            state.suspended = true;
+            let result = app.on_suspend(render_context);
+            dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop);
+        }
+
+        // Resumed is also sent once at startup, which isn't a resume from the app's view
+        Event::Resumed if state.suspended => {
+            state.suspended = false;
+            let result = app.on_resume(render_context);
+            dispatch_error(app, render_context, ErrorPhase::Event, result, event_loop);
+        }
+
+        Event::AboutToWait => {
+            if state.on_demand {
+                if render_context.take_redraw_request(Instant::now()) {
+                    state.redraw_pending = true;
                 }
 
-                _ => {}
-            };
-        })?;
+                if !state.redraw_pending {
+                    if let Some(deadline) = render_context.redraw_deadline() {
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(deadline));
+                    }
+                    return;
+                }
+            }
 
-        Ok(())
-    }
+            state.redraw_pending = false;
+            if let Some(window) = render_context.try_window() {
+                window.request_redraw();
+            }
+        }
+
+        _ => {}
+    };
 }
 
 fn create_window(settings: &AppSettings, event_loop: &EventLoop<()>) -> Result<Window> {
@@ -190,10 +473,175 @@ fn create_window(settings: &AppSettings, event_loop: &EventLoop<()>) -> Result<W
     Ok(window)
 }
 
-fn handle_error<T>(result: Result<T>, event_loop: &EventLoopWindowTarget<()>) -> Result<T> {
-    if let Err(error) = &result {
-        eprintln!("{}", error);
-        event_loop.exit();
+/// Let the app know if the window has been minimized or restored since the last check.
+fn update_minimized<A: App>(
+    app: &mut A,
+    ctx: &mut RenderContext,
+    minimized: &mut bool,
+) -> Result<()> {
+    // Not every platform can tell, but those that can't report a zero size instead
+    let Some(window) = ctx.try_window() else {
+        return Ok(());
+    };
+    let size = window.inner_size();
+    let is_minimized = window
+        .is_minimized()
+        .unwrap_or(size.width == 0 || size.height == 0);
+
+    if is_minimized != *minimized {
+        *minimized = is_minimized;
+        app.on_minimized(ctx, is_minimized)?;
+    }
+
+    Ok(())
+}
+
+/// Get the time to simulate for a redraw, from the real time since the last one.
+/// When waking from idle in on-demand mode, the time spent asleep isn't simulated: it counts as at
+/// most one target frame time, so input gets a single update rather than a burst catching up on
+/// the idle time. While animating, the whole time is passed on, and the frame loop limits it to
+/// the max frame time as usual.
+fn redraw_delta_time(
+    elapsed: Duration,
+    target_frame_time: Duration,
+    woke_from_idle: bool,
+) -> Duration {
+    if woke_from_idle {
+        elapsed.min(target_frame_time)
+    } else {
+        elapsed
+    }
+}
+
+/// Get the real time until the next timer fires, if the simulation clock is running.
+fn time_until_next_timer(ctx: &mut RenderContext, frame_loop: &FrameLoop) -> Option<Duration> {
+    let time_scale = ctx.time_scale() as f64;
+    if ctx.is_paused() || time_scale <= 0.0 {
+        return None;
+    }
+
+    let remaining = ctx.timers().next_deadline()?;
+    let wait = Duration::try_from_secs_f64(remaining.as_secs_f64() / time_scale).ok()?;
+
+    // Timers only fire on updates, so there's no point waking before the next one is owed
+    Some(wait.max(frame_loop.time_until_next_update(ctx.target_frame_time())))
+}
+
+/// Keep showing the crash screen until the window is closed.
+fn handle_crashed_event(
+    event: &Event<()>,
+    ctx: &mut RenderContext,
+    event_loop: &EventLoopWindowTarget<()>,
+) {
+    event_loop.set_control_flow(ControlFlow::Wait);
+
+    let Event::WindowEvent { event, .. } = event else {
+        return;
+    };
+
+    match event {
+        WindowEvent::CloseRequested => event_loop.exit(),
+
+        WindowEvent::Resized(size)
+            if size.width > 0
+                && size.height > 0
+                && ctx.resize_surface(size.width, size.height).is_ok() =>
+        {
+            if let Some(window) = ctx.try_window() {
+                window.request_redraw();
+            }
+        }
+
+        WindowEvent::RedrawRequested => {
+            if let Err(error) = ctx.present() {
+                eprintln!("{}", error);
+                event_loop.exit();
+            }
+        }
+
+        _ => {}
+    }
+}
+
+/// Let the app clean up, then stop the event loop.
+fn exit_app<A: App>(app: &mut A, ctx: &mut RenderContext, event_loop: &EventLoopWindowTarget<()>) {
+    // The app is exiting anyway, so the action doesn't matter
+    let result = app.on_exit(ctx);
+    let _ = handle_error(app, ctx, ErrorPhase::Exit, result);
+    event_loop.exit();
+}
+
+/// Let the app decide what to do about the result of an event handler, exiting if it asks to.
+/// Returns true if the rest of the event should be skipped.
+fn dispatch_error<A: App>(
+    app: &mut A,
+    ctx: &mut RenderContext,
+    phase: ErrorPhase,
+    result: Result<()>,
+    event_loop: &EventLoopWindowTarget<()>,
+) -> bool {
+    match handle_error(app, ctx, phase, result) {
+        Ok(ErrorAction::Continue) => false,
+        Ok(ErrorAction::SkipFrame) => true,
+        Ok(ErrorAction::Exit) | Err(_) => {
+            exit_app(app, ctx, event_loop);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimestepMode;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    fn headless_context() -> RenderContext {
+        RenderContext::new_headless(FRAME, FRAME * 5, 4, 4)
+    }
+
+    #[test]
+    fn idle_time_is_not_simulated() {
+        let idle = Duration::from_secs(60);
+
+        assert_eq!(redraw_delta_time(idle, FRAME, true), FRAME);
+        assert_eq!(redraw_delta_time(FRAME / 2, FRAME, true), FRAME / 2);
+        assert_eq!(redraw_delta_time(idle, FRAME, false), idle);
+    }
+
+    #[test]
+    fn timers_wake_the_loop_in_real_time() {
+        let mut ctx = headless_context();
+        let frame_loop = FrameLoop::new(TimestepMode::Variable, None);
+        assert_eq!(time_until_next_timer(&mut ctx, &frame_loop), None);
+
+        ctx.timers().after(Duration::from_millis(100), 1);
+        assert_eq!(
+            time_until_next_timer(&mut ctx, &frame_loop),
+            Some(Duration::from_millis(100))
+        );
+
+        ctx.set_time_scale(2.0);
+        assert_eq!(
+            time_until_next_timer(&mut ctx, &frame_loop),
+            Some(Duration::from_millis(50))
+        );
+
+        // The simulation clock is stopped, so the timer would never fire
+        ctx.set_time_scale(0.0);
+        assert_eq!(time_until_next_timer(&mut ctx, &frame_loop), None);
+        ctx.set_time_scale(1.0);
+        ctx.pause();
+        assert_eq!(time_until_next_timer(&mut ctx, &frame_loop), None);
+    }
+
+    #[test]
+    fn timers_wait_for_the_next_fixed_update() {
+        let mut ctx = headless_context();
+        let frame_loop = FrameLoop::new(TimestepMode::Fixed, None);
+        ctx.timers().after(Duration::from_millis(1), 1);
+
+        assert_eq!(time_until_next_timer(&mut ctx, &frame_loop), Some(FRAME));
     }
-    result
 }
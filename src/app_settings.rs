@@ -1,9 +1,40 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use muda::{Menu, Submenu};
 
 use crate::menu::{MenuDispatchMap, MenuItemWithAction};
 
+/// How elapsed time is turned into calls to `App::update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimestepMode {
+    /// Update once for every whole target frame time that has passed, always with the target
+    /// frame time as the delta time. Left over time is carried over to the next frame.
+    /// Simulations are deterministic, which suits games.
+    #[default]
+    Fixed,
+    /// Update exactly once per frame, with the real time since the last frame as the delta time.
+    /// Suits editors and tools, which only need to keep up with the display.
+    Variable,
+    /// Update as many times as needed to cover the real time since the last frame, in steps no
+    /// larger than the target frame time.
+    SemiFixed,
+}
+
+/// When the window is redrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RedrawMode {
+    /// Redraw as often as possible, for apps that are always animating.
+    #[default]
+    Continuous,
+    /// Sleep until something happens, then redraw once.
+    /// The window is redrawn when input arrives, a menu item is activated, a timer is due, or the
+    /// app calls `RenderContext::request_redraw` or `RenderContext::request_redraw_after`.
+    /// Time spent asleep counts as at most one target frame time, so the first update after
+    /// waking isn't handed the whole idle time as its delta time.
+    /// Suits tools that sit idle most of the time. Has no effect when running headless.
+    OnDemand,
+}
+
 /// Defines the settings for an App.
 pub struct AppSettings {
     /// The title of the window.
@@ -44,6 +75,34 @@ pub struct AppSettings {
     /// The maximum amount of time that can be taken by the `update` function before the `draw` function is called.
     /// Ideally this should be set to a multiple of the target frame time.
     pub(crate) max_frame_time: Duration,
+
+    /// How elapsed time is turned into calls to `update`.
+    pub(crate) timestep_mode: TimestepMode,
+
+    /// The maximum number of times `update` is called before each `draw`.
+    /// Any further time owed is thrown away and reported through `App::on_lag`.
+    pub(crate) max_updates_per_frame: Option<u32>,
+
+    /// When the window is redrawn.
+    pub(crate) redraw_mode: RedrawMode,
+
+    /// If true, a graph of recent frame times is drawn over the frame.
+    pub(crate) stats_overlay: bool,
+
+    /// If set, all input is recorded and saved to this file when the app exits.
+    pub(crate) input_recording: Option<PathBuf>,
+
+    /// If set, input is replayed from this recording instead of read from the window.
+    pub(crate) input_replay: Option<PathBuf>,
+
+    /// If set, panics in the app are caught, and a crash report is saved to this directory.
+    pub(crate) crash_report_dir: Option<PathBuf>,
+
+    /// If true, an error screen is shown after a crash, until the window is closed.
+    pub(crate) crash_screen: bool,
+
+    /// The version of the app, included in crash reports.
+    pub(crate) app_version: Option<String>,
 }
 
 impl AppSettings {
@@ -67,6 +126,15 @@ pub struct AppBuilder {
     menu_dispatch_map: MenuDispatchMap,
     target_frame_time: Option<Duration>,
     max_frame_time: Option<Duration>,
+    timestep_mode: TimestepMode,
+    max_updates_per_frame: Option<u32>,
+    redraw_mode: RedrawMode,
+    stats_overlay: bool,
+    input_recording: Option<PathBuf>,
+    input_replay: Option<PathBuf>,
+    crash_report_dir: Option<PathBuf>,
+    crash_screen: bool,
+    app_version: Option<String>,
 }
 
 impl AppBuilder {
@@ -82,6 +150,15 @@ impl AppBuilder {
             menu_dispatch_map: MenuDispatchMap::new(),
             target_frame_time: None,
             max_frame_time: None,
+            timestep_mode: TimestepMode::Fixed,
+            max_updates_per_frame: None,
+            redraw_mode: RedrawMode::Continuous,
+            stats_overlay: false,
+            input_recording: None,
+            input_replay: None,
+            crash_report_dir: None,
+            crash_screen: false,
+            app_version: None,
         }
     }
 
@@ -119,7 +196,16 @@ impl AppBuilder {
         self
     }
 
+    /// Set the time between updates with a fixed timestep, and the largest step of a semi-fixed
+    /// timestep.
+    ///
+    /// # Panics
+    /// Panics if `target_frame_time` is zero.
     pub fn with_target_frame_time(mut self, target_frame_time: Duration) -> Self {
+        assert!(
+            !target_frame_time.is_zero(),
+            "Target frame time must be greater than zero"
+        );
         self.target_frame_time = Some(target_frame_time);
         self
     }
@@ -129,6 +215,66 @@ impl AppBuilder {
         self
     }
 
+    pub fn with_timestep_mode(mut self, timestep_mode: TimestepMode) -> Self {
+        self.timestep_mode = timestep_mode;
+        self
+    }
+
+    /// Limit how many times `update` is called before each `draw`, so a slow update can't cause
+    /// a growing backlog of updates. Time past the limit is thrown away and reported through
+    /// `App::on_lag`.
+    pub fn with_max_updates_per_frame(mut self, max_updates: u32) -> Self {
+        self.max_updates_per_frame = Some(max_updates);
+        self
+    }
+
+    pub fn with_redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+        self.redraw_mode = redraw_mode;
+        self
+    }
+
+    /// Draw a graph of recent frame times over the frame.
+    /// Can also be toggled at runtime with `RenderContext::set_stats_overlay`.
+    pub fn with_stats_overlay(mut self, visible: bool) -> Self {
+        self.stats_overlay = visible;
+        self
+    }
+
+    /// Record all input, and save it to a file when the app exits.
+    pub fn with_input_recording(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input_recording = Some(path.into());
+        self
+    }
+
+    /// Replay input from a recording, instead of reading it from the window.
+    pub fn with_input_replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input_replay = Some(path.into());
+        self
+    }
+
+    /// Catch panics in `App::run`, and save a crash report to the directory.
+    /// The report includes the backtrace, the app version, recent frame stats and the frame
+    /// buffer as a PNG. The frame buffer is saved as it was when the panic happened, so a panic in
+    /// `App::draw` leaves it partly drawn. The app exits afterwards, unless the crash screen is
+    /// enabled.
+    pub fn with_crash_reports(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.crash_report_dir = Some(dir.into());
+        self
+    }
+
+    /// After a crash, show an error screen in the window until it is closed, instead of exiting.
+    /// Has no effect unless crash reports are enabled.
+    pub fn with_crash_screen(mut self, enabled: bool) -> Self {
+        self.crash_screen = enabled;
+        self
+    }
+
+    /// Set the version of the app, included in crash reports, e.g. `env!("CARGO_PKG_VERSION")`.
+    pub fn with_app_version(mut self, version: impl Into<String>) -> Self {
+        self.app_version = Some(version.into());
+        self
+    }
+
     pub fn build(self) -> AppSettings {
         AppSettings {
             window_title: self.window_title.unwrap_or_else(|| "App".to_string()),
@@ -145,6 +291,15 @@ impl AppBuilder {
             max_frame_time: self
                 .max_frame_time
                 .unwrap_or_else(|| Duration::from_millis(32)),
+            timestep_mode: self.timestep_mode,
+            max_updates_per_frame: self.max_updates_per_frame,
+            redraw_mode: self.redraw_mode,
+            stats_overlay: self.stats_overlay,
+            input_recording: self.input_recording,
+            input_replay: self.input_replay,
+            crash_report_dir: self.crash_report_dir,
+            crash_screen: self.crash_screen,
+            app_version: self.app_version,
         }
     }
 }
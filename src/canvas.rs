@@ -0,0 +1,711 @@
+use crate::{BitmapFont, Color, Image, Rect, TextOptions};
+
+#[cfg(feature = "ttf")]
+use crate::TrueTypeFont;
+
+/// How source pixels are combined with the pixels already on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Replace the destination pixel, including its alpha.
+    Replace,
+    /// Composite the source pixel over the destination, using the Porter-Duff "over" operator.
+    #[default]
+    Alpha,
+}
+
+/// Options for drawing an image with `Canvas::blit_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlitOptions {
+    /// The region of the image to draw. If not set, the whole image is drawn.
+    pub source: Option<Rect>,
+    /// Mirror the image horizontally.
+    pub flip_x: bool,
+    /// Mirror the image vertically.
+    pub flip_y: bool,
+    /// If set, pixels with this RGB value are skipped, regardless of their alpha.
+    pub color_key: Option<Color>,
+    /// If set, each pixel is multiplied by this color before it is drawn.
+    pub tint: Option<Color>,
+    /// How pixels are combined with the canvas.
+    pub blend: BlendMode,
+}
+
+impl BlitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_source(mut self, source: Rect) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn with_color_key(mut self, color_key: Color) -> Self {
+        self.color_key = Some(color_key);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+}
+
+/// A drawing surface over an RGBA frame buffer.
+/// All drawing operations are clipped to the bounds of the frame, so coordinates outside of it
+/// are safe to use.
+#[derive(Debug)]
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> Canvas<'a> {
+    /// Create a canvas over an RGBA frame buffer of the given size.
+    ///
+    /// # Panics
+    /// Panics if the length of `frame` is not `width * height * 4`.
+    pub fn new(frame: &'a mut [u8], width: u32, height: u32) -> Self {
+        assert_eq!(
+            frame.len(),
+            width as usize * height as usize * 4,
+            "Frame buffer does not match a {}x{} RGBA frame",
+            width,
+            height
+        );
+
+        Self {
+            frame,
+            width,
+            height,
+        }
+    }
+
+    /// Get the width of the canvas, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the canvas, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the raw RGBA frame buffer
+    pub fn frame(&self) -> &[u8] {
+        self.frame
+    }
+
+    /// Get the raw RGBA frame buffer (mutable)
+    pub fn frame_mut(&mut self) -> &mut [u8] {
+        self.frame
+    }
+
+    /// Returns true if the point is inside the canvas.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if self.contains(x, y) {
+            Some((y as usize * self.width as usize + x as usize) * 4)
+        } else {
+            None
+        }
+    }
+
+    /// Set the color of a single pixel.
+    /// Does nothing if the pixel is outside of the canvas.
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.frame[i..i + 4].copy_from_slice(&color.to_array());
+        }
+    }
+
+    /// Get the color of a single pixel.
+    /// Returns `None` if the pixel is outside of the canvas.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+        self.index(x, y).map(|i| {
+            Color::from_array([
+                self.frame[i],
+                self.frame[i + 1],
+                self.frame[i + 2],
+                self.frame[i + 3],
+            ])
+        })
+    }
+
+    /// Composite a color over a single pixel, using the Porter-Duff "over" operator.
+    /// Does nothing if the pixel is outside of the canvas.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.blend_at(i, color);
+        }
+    }
+
+    fn blend_at(&mut self, i: usize, color: Color) {
+        let pixel = &mut self.frame[i..i + 4];
+        let dst = Color::from_array([pixel[0], pixel[1], pixel[2], pixel[3]]);
+        pixel.copy_from_slice(&color.over(dst).to_array());
+    }
+
+    /// Fill the whole canvas with a color.
+    pub fn clear(&mut self, color: Color) {
+        let rgba = color.to_array();
+        for pixel in self.frame.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    /// Draw a horizontal line from `x0` to `x1` (inclusive).
+    fn hline(&mut self, x0: i32, x1: i32, y: i32, color: Color) {
+        if y < 0 || y as u32 >= self.height {
+            return;
+        }
+
+        let start = x0.min(x1).max(0);
+        let end = x0.max(x1).min(self.width as i32 - 1);
+        if start > end {
+            return;
+        }
+
+        let row = y as usize * self.width as usize;
+        let rgba = color.to_array();
+        let span = &mut self.frame[(row + start as usize) * 4..(row + end as usize + 1) * 4];
+        for pixel in span.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    /// Fill a rectangle with a color.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let right = x.saturating_add(width - 1);
+        for row in y.max(0)..y.saturating_add(height).min(self.height as i32) {
+            self.hline(x, right, row, color);
+        }
+    }
+
+    /// Draw the 1 pixel outline of a rectangle.
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let right = x.saturating_add(width - 1);
+        let bottom = y.saturating_add(height - 1);
+
+        self.hline(x, right, y, color);
+        self.hline(x, right, bottom, color);
+        for row in y.saturating_add(1).max(0)..bottom.min(self.height as i32) {
+            self.set_pixel(x, row, color);
+            self.set_pixel(right, row, color);
+        }
+    }
+
+    /// Draw a line between two points (inclusive), using Bresenham's algorithm.
+    /// The line is clipped to the canvas first, so distant endpoints don't cost any extra steps.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let Some((x0, y0, x1, y1)) = self.clip_line(x0, y0, x1, y1) else {
+            return;
+        };
+
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+
+        loop {
+            self.set_pixel(x, y, color);
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let error2 = error * 2;
+            if error2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if error2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Clip a line to the canvas, using the Liang-Barsky algorithm.
+    /// Returns the endpoints of the visible part, or `None` if none of it is on the canvas.
+    fn clip_line(&self, x0: i32, y0: i32, x1: i32, y1: i32) -> Option<(i32, i32, i32, i32)> {
+        if self.contains(x0, y0) && self.contains(x1, y1) {
+            return Some((x0, y0, x1, y1));
+        }
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        // Work in f64 so that the difference between two i32 coordinates can't overflow
+        let (x0, y0) = (x0 as f64, y0 as f64);
+        let (dx, dy) = (x1 as f64 - x0, y1 as f64 - y0);
+        let max_x = (self.width - 1) as f64;
+        let max_y = (self.height - 1) as f64;
+
+        let (mut start, mut end) = (0.0f64, 1.0f64);
+        for (p, q) in [(-dx, x0), (dx, max_x - x0), (-dy, y0), (dy, max_y - y0)] {
+            if p == 0.0 {
+                // Parallel to this edge, so the line is either all inside or all outside of it
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                start = start.max(q / p);
+            } else {
+                end = end.min(q / p);
+            }
+        }
+        if start > end {
+            return None;
+        }
+
+        let point = |t: f64| ((x0 + t * dx).round() as i32, (y0 + t * dy).round() as i32);
+        let (x0, y0) = point(start);
+        let (x1, y1) = point(end);
+        Some((x0, y0, x1, y1))
+    }
+
+    /// Draw the 1 pixel outline of a circle, using the midpoint circle algorithm.
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: Color) {
+        circle_octants(radius, |x, y| {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y)] {
+                self.set_pixel(cx + px, cy + py, color);
+                self.set_pixel(cx + px, cy - py, color);
+            }
+        });
+    }
+
+    /// Fill a circle with a color.
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32, color: Color) {
+        circle_octants(radius, |x, y| {
+            self.hline(cx - x, cx + x, cy + y, color);
+            self.hline(cx - x, cx + x, cy - y, color);
+            self.hline(cx - y, cx + y, cy + x, color);
+            self.hline(cx - y, cx + y, cy - x, color);
+        });
+    }
+
+    /// Draw the 1 pixel outline of an axis-aligned ellipse, using the midpoint ellipse algorithm.
+    pub fn draw_ellipse(&mut self, cx: i32, cy: i32, radius_x: i32, radius_y: i32, color: Color) {
+        ellipse_quadrant(radius_x, radius_y, |x, y| {
+            self.set_pixel(cx + x, cy + y, color);
+            self.set_pixel(cx - x, cy + y, color);
+            self.set_pixel(cx + x, cy - y, color);
+            self.set_pixel(cx - x, cy - y, color);
+        });
+    }
+
+    /// Fill an axis-aligned ellipse with a color.
+    pub fn fill_ellipse(&mut self, cx: i32, cy: i32, radius_x: i32, radius_y: i32, color: Color) {
+        ellipse_quadrant(radius_x, radius_y, |x, y| {
+            self.hline(cx - x, cx + x, cy + y, color);
+            self.hline(cx - x, cx + x, cy - y, color);
+        });
+    }
+
+    /// Draw the 1 pixel outline of a triangle.
+    pub fn draw_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+        self.draw_polygon(&[a, b, c], color);
+    }
+
+    /// Fill a triangle with a color.
+    pub fn fill_triangle(&mut self, a: (i32, i32), b: (i32, i32), c: (i32, i32), color: Color) {
+        self.fill_polygon(&[a, b, c], color);
+    }
+
+    /// Draw the 1 pixel outline of a closed polygon.
+    pub fn draw_polygon(&mut self, points: &[(i32, i32)], color: Color) {
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            self.draw_line(x0, y0, x1, y1, color);
+        }
+    }
+
+    /// Fill a closed polygon with a color.
+    /// Pixels are filled if their center is inside the polygon, using the even-odd rule, so both
+    /// convex and concave (or self-intersecting) polygons are supported.
+    pub fn fill_polygon(&mut self, points: &[(i32, i32)], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().map(|p| p.1).min().unwrap_or(0).max(0);
+        let max_y = points
+            .iter()
+            .map(|p| p.1)
+            .max()
+            .unwrap_or(0)
+            .min(self.height as i32 - 1);
+
+        let mut crossings = Vec::with_capacity(points.len());
+        for y in min_y..=max_y {
+            let sample_y = y as f64 + 0.5;
+
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                let (y0, y1) = (y0 as f64, y1 as f64);
+
+                if (y0 <= sample_y && y1 > sample_y) || (y1 <= sample_y && y0 > sample_y) {
+                    let t = (sample_y - y0) / (y1 - y0);
+                    crossings.push(x0 as f64 + t * (x1 - x0) as f64);
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil() as i32;
+                let end = (span[1] - 0.5).ceil() as i32 - 1;
+                if start <= end {
+                    self.hline(start, end, y, color);
+                }
+            }
+        }
+    }
+
+    /// Draw an image with its top left corner at `(x, y)`, alpha blending it over the canvas.
+    pub fn blit(&mut self, image: &Image, x: i32, y: i32) {
+        self.blit_with(image, x, y, &BlitOptions::default());
+    }
+
+    /// Draw a region of an image with its top left corner at `(x, y)`, alpha blending it over the
+    /// canvas.
+    pub fn blit_region(&mut self, image: &Image, source: Rect, x: i32, y: i32) {
+        self.blit_with(image, x, y, &BlitOptions::new().with_source(source));
+    }
+
+    /// Draw an image with its top left corner at `(x, y)`.
+    /// The source region is clipped to the image, and the destination is clipped to the canvas.
+    pub fn blit_with(&mut self, image: &Image, x: i32, y: i32, options: &BlitOptions) {
+        let source = options.source.unwrap_or_else(|| image.bounds());
+        let Some(source) = source.intersection(&image.bounds()) else {
+            return;
+        };
+
+        let destination = Rect::new(x, y, source.width, source.height);
+        let canvas_bounds = Rect::new(0, 0, self.width, self.height);
+        let Some(clipped) = destination.intersection(&canvas_bounds) else {
+            return;
+        };
+
+        let color_key = options.color_key.map(|key| (key.r, key.g, key.b));
+
+        for dst_y in clipped.y..clipped.bottom() {
+            let v = dst_y - y;
+            let src_y = if options.flip_y {
+                source.bottom() - 1 - v
+            } else {
+                source.y + v
+            };
+
+            for dst_x in clipped.x..clipped.right() {
+                let u = dst_x - x;
+                let src_x = if options.flip_x {
+                    source.right() - 1 - u
+                } else {
+                    source.x + u
+                };
+
+                let Some(mut color) = image.get_pixel(src_x, src_y) else {
+                    continue;
+                };
+
+                if color_key == Some((color.r, color.g, color.b)) {
+                    continue;
+                }
+
+                if let Some(tint) = options.tint {
+                    color = color.multiply(tint);
+                }
+
+                let i = (dst_y as usize * self.width as usize + dst_x as usize) * 4;
+                match options.blend {
+                    BlendMode::Replace => {
+                        self.frame[i..i + 4].copy_from_slice(&color.to_array());
+                    }
+                    BlendMode::Alpha => self.blend_at(i, color),
+                }
+            }
+        }
+    }
+
+    /// Draw a line of text with the built-in 8x8 font, with its top left corner at `(x, y)`.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        self.draw_text_with(
+            BitmapFont::default_font(),
+            x,
+            y,
+            text,
+            &TextOptions::new(color),
+        );
+    }
+
+    /// Draw a block of text with a bitmap font, with its top left corner at `(x, y)`.
+    pub fn draw_text_with(
+        &mut self,
+        font: &BitmapFont,
+        x: i32,
+        y: i32,
+        text: &str,
+        options: &TextOptions,
+    ) {
+        for positioned in font.layout(text, options) {
+            let Some(page) = font.pages().get(positioned.glyph.page) else {
+                continue;
+            };
+
+            let blit = BlitOptions::new()
+                .with_source(positioned.glyph.source)
+                .with_tint(options.color);
+            self.blit_with(page, x + positioned.x, y + positioned.y, &blit);
+        }
+    }
+
+    /// Draw a block of text with a TrueType font at a pixel size, with its top left corner at
+    /// `(x, y)`.
+    #[cfg(feature = "ttf")]
+    pub fn draw_ttf_text(
+        &mut self,
+        font: &mut TrueTypeFont,
+        x: i32,
+        y: i32,
+        text: &str,
+        size: f32,
+        options: &TextOptions,
+    ) {
+        let color = options.color;
+
+        for (glyph, pen_x, baseline) in font.layout(text, size, options) {
+            let raster = font.rasterize(glyph, size);
+            let left = x + pen_x + raster.offset_x;
+            let top = y + baseline + raster.offset_y;
+
+            for row in 0..raster.height {
+                for column in 0..raster.width {
+                    let coverage = raster.coverage[(row * raster.width + column) as usize];
+                    if coverage == 0 {
+                        continue;
+                    }
+
+                    let alpha = (color.a as u32 * coverage as u32 + 0x7f) / 0xff;
+                    self.blend_pixel(
+                        left + column as i32,
+                        top + row as i32,
+                        color.with_alpha(alpha as u8),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Walk the first octant of a circle, calling `plot` with each `(x, y)` offset from the center.
+fn circle_octants(radius: i32, mut plot: impl FnMut(i32, i32)) {
+    if radius < 0 {
+        return;
+    }
+
+    let (mut x, mut y) = (radius, 0);
+    let mut error = 1 - radius;
+
+    while x >= y {
+        plot(x, y);
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Walk one quadrant of an ellipse, calling `plot` with each `(x, y)` offset from the center.
+fn ellipse_quadrant(radius_x: i32, radius_y: i32, mut plot: impl FnMut(i32, i32)) {
+    if radius_x < 0 || radius_y < 0 {
+        return;
+    }
+
+    // A flat ellipse is a horizontal line, which the second region below never reaches
+    if radius_y == 0 {
+        for x in 0..=radius_x {
+            plot(x, 0);
+        }
+        return;
+    }
+
+    let rx2 = radius_x as i64 * radius_x as i64;
+    let ry2 = radius_y as i64 * radius_y as i64;
+
+    let (mut x, mut y) = (0i64, radius_y as i64);
+    let mut px = 0;
+    let mut py = 2 * rx2 * y;
+
+    // Region 1: the slope is shallower than -1, so step along x
+    let mut p = ry2 - rx2 * radius_y as i64 + rx2 / 4;
+    while px < py {
+        plot(x as i32, y as i32);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += ry2 + px;
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += ry2 + px - py;
+        }
+    }
+
+    // Region 2: the slope is steeper than -1, so step along y
+    p = (ry2 * (2 * x + 1) * (2 * x + 1) + 4 * rx2 * (y - 1) * (y - 1) - 4 * rx2 * ry2) / 4;
+    while y >= 0 {
+        plot(x as i32, y as i32);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += rx2 - py;
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += rx2 - py + px;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render an image as rows of `#` for set pixels and `.` for transparent ones.
+    fn pixels(image: &Image) -> Vec<String> {
+        (0..image.height() as i32)
+            .map(|y| {
+                (0..image.width() as i32)
+                    .map(|x| match image.get_pixel(x, y) {
+                        Some(Color::TRANSPARENT) => '.',
+                        _ => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_rect_is_clipped_to_the_edges() {
+        let mut image = Image::new(4, 4);
+        image.canvas().fill_rect(-2, -2, 4, 3, Color::RED);
+        image.canvas().fill_rect(3, 2, 10, 10, Color::RED);
+
+        assert_eq!(pixels(&image), ["##..", "....", "...#", "...#"]);
+    }
+
+    #[test]
+    fn shapes_with_extreme_coordinates_are_clipped() {
+        let mut image = Image::new(4, 4);
+        let mut canvas = image.canvas();
+        canvas.fill_rect(i32::MAX, i32::MAX, i32::MAX, i32::MAX, Color::RED);
+        canvas.fill_rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX, Color::RED);
+        canvas.draw_rect(i32::MIN, 1, i32::MAX, i32::MAX, Color::RED);
+        assert_eq!(pixels(&image), ["....", "....", "....", "...."]);
+
+        image
+            .canvas()
+            .fill_rect(2, 3, i32::MAX, i32::MAX, Color::RED);
+        assert_eq!(pixels(&image), ["....", "....", "....", "..##"]);
+    }
+
+    #[test]
+    fn draw_line_clips_distant_endpoints() {
+        let mut image = Image::new(4, 4);
+        // Without clipping, this would step through billions of off-screen pixels
+        image
+            .canvas()
+            .draw_line(i32::MIN, 1, i32::MAX, 1, Color::RED);
+        image.canvas().draw_line(2, 2, 2, i32::MAX, Color::RED);
+        assert_eq!(pixels(&image), ["....", "####", "..#.", "..#."]);
+
+        let mut image = Image::new(4, 4);
+        image.canvas().draw_line(-4, -4, 7, 7, Color::RED);
+        image.canvas().draw_line(-10, 5, 5, -10, Color::RED);
+        assert_eq!(pixels(&image), ["#...", ".#..", "..#.", "...#"]);
+    }
+
+    #[test]
+    fn draw_line_on_the_canvas_includes_both_endpoints() {
+        let mut image = Image::new(4, 4);
+        image.canvas().draw_line(3, 0, 0, 2, Color::RED);
+
+        assert_eq!(pixels(&image), ["...#", ".##.", "#...", "...."]);
+    }
+
+    #[test]
+    fn blit_clips_negative_positions() {
+        let mut sprite = Image::new(2, 2);
+        sprite.canvas().set_pixel(1, 1, Color::RED);
+
+        let mut image = Image::new(4, 4);
+        image.canvas().blit(&sprite, -1, -1);
+        image.canvas().blit(&sprite, 2, 2);
+        image.canvas().blit(&sprite, i32::MIN, i32::MAX);
+
+        assert_eq!(pixels(&image), ["#...", "....", "....", "...#"]);
+    }
+
+    #[test]
+    fn blit_with_flips_and_color_keys() {
+        let mut sprite = Image::filled(2, 1, Color::MAGENTA);
+        sprite.canvas().set_pixel(0, 0, Color::RED);
+
+        let mut image = Image::new(2, 1);
+        let options = BlitOptions::new()
+            .with_flip(true, false)
+            .with_color_key(Color::MAGENTA.with_alpha(0));
+        image.canvas().blit_with(&sprite, 0, 0, &options);
+
+        assert_eq!(image.get_pixel(0, 0), Some(Color::TRANSPARENT));
+        assert_eq!(image.get_pixel(1, 0), Some(Color::RED));
+    }
+
+    #[test]
+    fn blit_blends_over_the_canvas() {
+        let sprite = Image::filled(1, 1, Color::rgba(0xff, 0, 0, 0x80));
+
+        let mut image = Image::filled(1, 1, Color::BLUE);
+        image.canvas().blit(&sprite, 0, 0);
+        assert_eq!(
+            image.get_pixel(0, 0),
+            Some(Color::rgba(0xff, 0, 0, 0x80).over(Color::BLUE))
+        );
+
+        let mut image = Image::filled(1, 1, Color::BLUE);
+        let options = BlitOptions::new().with_blend(BlendMode::Replace);
+        image.canvas().blit_with(&sprite, 0, 0, &options);
+        assert_eq!(image.get_pixel(0, 0), Some(Color::rgba(0xff, 0, 0, 0x80)));
+    }
+}
@@ -0,0 +1,296 @@
+use super::{decode_error, rgba_len, ImageErrorKind, ImageFormat};
+use crate::{Color, Image};
+
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_RLE4: u32 = 2;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+const FILE_HEADER_SIZE: usize = 14;
+
+fn error(section: &str, message: impl Into<String>) -> ImageErrorKind {
+    decode_error(ImageFormat::Bmp, section, message)
+}
+
+fn read_u16(bytes: &[u8], offset: usize, section: &str) -> Result<u16, ImageErrorKind> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| error(section, "unexpected end of file"))
+}
+
+fn read_u32(bytes: &[u8], offset: usize, section: &str) -> Result<u32, ImageErrorKind> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| error(section, "unexpected end of file"))
+}
+
+/// The red, green, blue and alpha masks of a 16 or 32 bit pixel.
+#[derive(Clone, Copy)]
+struct Masks([u32; 4]);
+
+impl Masks {
+    fn color(&self, value: u32) -> Color {
+        let [r, g, b, a] = self.0.map(|mask| extract(value, mask));
+        // A missing alpha mask means the image is opaque
+        let a = if self.0[3] == 0 { 0xff } else { a };
+        Color::rgba(r, g, b, a)
+    }
+}
+
+/// Extract a channel from a pixel, and scale it to 8 bits.
+fn extract(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let channel = (value & mask) >> shift;
+
+    if bits >= 8 {
+        (channel >> (bits - 8)) as u8
+    } else {
+        (channel * 0xff / ((1 << bits) - 1)) as u8
+    }
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Image, ImageErrorKind> {
+    if bytes.len() < FILE_HEADER_SIZE || !bytes.starts_with(b"BM") {
+        return Err(error("file header", "not a BMP file"));
+    }
+
+    let pixel_offset = read_u32(bytes, 10, "file header")? as usize;
+    let info = &bytes[FILE_HEADER_SIZE..];
+    let header_size = read_u32(info, 0, "info header")? as usize;
+
+    // Older OS/2 style headers use unsigned 16 bit dimensions, so are always stored bottom to
+    // top, and use 3 byte palette entries
+    let (width, height, bits_per_pixel, compression, colors_used, palette_entry_size) =
+        match header_size {
+            12 => (
+                read_u16(info, 4, "info header")? as i32,
+                read_u16(info, 6, "info header")? as i32,
+                read_u16(info, 10, "info header")?,
+                BI_RGB,
+                0,
+                3,
+            ),
+            40.. => (
+                read_u32(info, 4, "info header")? as i32,
+                read_u32(info, 8, "info header")? as i32,
+                read_u16(info, 14, "info header")?,
+                read_u32(info, 16, "info header")?,
+                read_u32(info, 32, "info header")?,
+                4,
+            ),
+            size => {
+                return Err(error(
+                    "info header",
+                    format!("unsupported header size {}", size),
+                ));
+            }
+        };
+
+    if width <= 0 || height == 0 {
+        return Err(error("info header", "image has no pixels"));
+    }
+
+    // A negative height means rows are stored top to bottom
+    let top_down = height < 0;
+    let (width, height) = (width as u32, height.unsigned_abs());
+
+    let masks = match compression {
+        BI_RGB => match bits_per_pixel {
+            16 => Masks([0x7c00, 0x03e0, 0x001f, 0]),
+            _ => Masks([0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0]),
+        },
+        BI_BITFIELDS | BI_ALPHABITFIELDS => {
+            if bits_per_pixel != 16 && bits_per_pixel != 32 {
+                return Err(error(
+                    "info header",
+                    "bitfields are only valid for 16 and 32 bit images",
+                ));
+            }
+
+            // Masks are part of V2+ headers, otherwise they directly follow the header
+            let section = if header_size > 40 {
+                "info header"
+            } else {
+                "color masks"
+            };
+            let alpha = if header_size >= 56 || compression == BI_ALPHABITFIELDS {
+                read_u32(info, 52, section)?
+            } else {
+                0
+            };
+            Masks([
+                read_u32(info, 40, section)?,
+                read_u32(info, 44, section)?,
+                read_u32(info, 48, section)?,
+                alpha,
+            ])
+        }
+        BI_RLE8 | BI_RLE4 => {
+            return Err(error(
+                "info header",
+                "RLE compressed bitmaps are not supported",
+            ));
+        }
+        compression => {
+            return Err(error(
+                "info header",
+                format!("unsupported compression type {}", compression),
+            ));
+        }
+    };
+
+    let palette: Vec<Color> = if bits_per_pixel <= 8 {
+        let count = if colors_used == 0 || colors_used > 1 << bits_per_pixel {
+            1 << bits_per_pixel
+        } else {
+            colors_used as usize
+        };
+        let start = FILE_HEADER_SIZE + header_size;
+        let end = start + count * palette_entry_size;
+        let entries = bytes
+            .get(start..end)
+            .ok_or_else(|| error("palette", "unexpected end of file"))?;
+
+        entries
+            .chunks_exact(palette_entry_size)
+            .map(|bgr| Color::rgb(bgr[2], bgr[1], bgr[0]))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if !matches!(bits_per_pixel, 1 | 4 | 8 | 16 | 24 | 32) {
+        return Err(error(
+            "info header",
+            format!("unsupported bit depth {}", bits_per_pixel),
+        ));
+    }
+
+    if rgba_len(width, height).is_none() {
+        return Err(error("info header", "image is too large"));
+    }
+
+    let stride = (width as usize * bits_per_pixel as usize).div_ceil(32) * 4;
+    let pixels = stride
+        .checked_mul(height as usize)
+        .and_then(|len| bytes.get(pixel_offset..pixel_offset.checked_add(len)?))
+        .ok_or_else(|| error("pixel data", "pixel data is truncated"))?;
+
+    let mut image = Image::new(width, height);
+    for (row_index, row) in pixels.chunks_exact(stride).enumerate() {
+        let y = if top_down {
+            row_index
+        } else {
+            height as usize - 1 - row_index
+        };
+
+        for x in 0..width as usize {
+            let color = match bits_per_pixel {
+                1 | 4 | 8 => {
+                    let bit = x * bits_per_pixel as usize;
+                    let shift = 8 - bits_per_pixel as usize - bit % 8;
+                    let index = (row[bit / 8] >> shift) as usize & ((1 << bits_per_pixel) - 1);
+                    *palette.get(index).ok_or_else(|| {
+                        error(
+                            "pixel data",
+                            format!("palette index {} is out of range", index),
+                        )
+                    })?
+                }
+                16 => masks.color(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32),
+                24 => Color::rgb(row[x * 3 + 2], row[x * 3 + 1], row[x * 3]),
+                _ => masks.color(u32::from_le_bytes([
+                    row[x * 4],
+                    row[x * 4 + 1],
+                    row[x * 4 + 2],
+                    row[x * 4 + 3],
+                ])),
+            };
+
+            let i = (y * width as usize + x) * 4;
+            image.data_mut()[i..i + 4].copy_from_slice(&color.to_array());
+        }
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a 24 bit BMP with a 40 byte info header from rows of pixels, in the stored order.
+    fn build(width: u32, height: i32, rows: &[&[[u8; 3]]]) -> Vec<u8> {
+        let stride = (width as usize * 3).div_ceil(4) * 4;
+        let pixel_offset = FILE_HEADER_SIZE + 40;
+
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&((pixel_offset + stride * rows.len()) as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&width.to_le_bytes());
+        bmp.extend_from_slice(&height.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        bmp.extend_from_slice(&BI_RGB.to_le_bytes());
+        bmp.extend_from_slice(&[0; 20]);
+
+        for row in rows {
+            let start = bmp.len();
+            for [r, g, b] in row.iter() {
+                bmp.extend_from_slice(&[*b, *g, *r]);
+            }
+            bmp.resize(start + stride, 0);
+        }
+        bmp
+    }
+
+    const RED: [u8; 3] = [0xff, 0x00, 0x00];
+    const BLUE: [u8; 3] = [0x00, 0x00, 0xff];
+
+    #[test]
+    fn decodes_bottom_up_rows() {
+        let bmp = build(2, 2, &[&[RED, RED], &[BLUE, BLUE]]);
+
+        let image = decode(&bmp).unwrap();
+        assert_eq!(image.get_pixel(1, 0), Some(Color::rgb(0x00, 0x00, 0xff)));
+        assert_eq!(image.get_pixel(1, 1), Some(Color::rgb(0xff, 0x00, 0x00)));
+    }
+
+    #[test]
+    fn decodes_top_down_rows() {
+        let bmp = build(2, -2, &[&[RED, RED], &[BLUE, BLUE]]);
+
+        let image = decode(&bmp).unwrap();
+        assert_eq!(image.get_pixel(1, 0), Some(Color::rgb(0xff, 0x00, 0x00)));
+        assert_eq!(image.get_pixel(1, 1), Some(Color::rgb(0x00, 0x00, 0xff)));
+    }
+
+    #[test]
+    fn decodes_os2_headers_bottom_up() {
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&[0; 8]);
+        bmp.extend_from_slice(&((FILE_HEADER_SIZE + 12) as u32).to_le_bytes());
+        bmp.extend_from_slice(&12u32.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&2u16.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        // Two rows of one pixel, each padded to 4 bytes
+        bmp.extend_from_slice(&[0xff, 0x00, 0x00, 0, 0x00, 0x00, 0xff, 0]);
+
+        let image = decode(&bmp).unwrap();
+        assert_eq!(image.get_pixel(0, 0), Some(Color::rgb(0xff, 0x00, 0x00)));
+        assert_eq!(image.get_pixel(0, 1), Some(Color::rgb(0x00, 0x00, 0xff)));
+    }
+}
@@ -0,0 +1,184 @@
+//! Decoders for loading `Image`s from common file formats, and a PNG encoder for saving them.
+//! Each format is behind a cargo feature of the same name, so apps only pay for the decoders they
+//! use.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::Image;
+
+#[cfg(feature = "bmp")]
+mod bmp;
+#[cfg(feature = "png")]
+mod png;
+#[cfg(feature = "qoi")]
+mod qoi;
+
+/// An image file format that can be decoded into an `Image`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+    Qoi,
+}
+
+impl ImageFormat {
+    /// Detect the format of an encoded image from its leading bytes.
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(b"BM") {
+            Some(Self::Bmp)
+        } else if bytes.starts_with(b"qoif") {
+            Some(Self::Qoi)
+        } else {
+            None
+        }
+    }
+
+    /// The cargo feature that enables decoding this format.
+    pub fn feature(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Bmp => "bmp",
+            Self::Qoi => "qoi",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Png => "PNG",
+            Self::Bmp => "BMP",
+            Self::Qoi => "QOI",
+        })
+    }
+}
+
+/// The reason an image failed to load.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ImageErrorKind {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The data is not in a recognised image format.
+    UnknownFormat,
+    /// The data is in a known format, but its cargo feature is not enabled.
+    FormatDisabled(ImageFormat),
+    /// The data is corrupt, or uses a part of the format that is not supported.
+    Decode {
+        format: ImageFormat,
+        /// The chunk or section of the file that failed, e.g. `IDAT` or `info header`.
+        chunk: String,
+        message: String,
+    },
+}
+
+/// An error from loading an image, including the file it came from, if any.
+#[derive(Debug)]
+pub struct ImageError {
+    path: Option<PathBuf>,
+    kind: ImageErrorKind,
+}
+
+impl ImageError {
+    /// The file that failed to load, if the image was loaded from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The reason the image failed to load.
+    pub fn kind(&self) -> &ImageErrorKind {
+        &self.kind
+    }
+
+    pub(crate) fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl From<ImageErrorKind> for ImageError {
+    fn from(kind: ImageErrorKind) -> Self {
+        Self { path: None, kind }
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "Failed to load image {}: ", path.display())?;
+        } else {
+            write!(f, "Failed to load image: ")?;
+        }
+
+        match &self.kind {
+            ImageErrorKind::Io(error) => write!(f, "{}", error),
+            ImageErrorKind::UnknownFormat => write!(f, "unrecognised image format"),
+            ImageErrorKind::FormatDisabled(format) => write!(
+                f,
+                "{} support is disabled, enable the `{}` feature of perovskite",
+                format,
+                format.feature()
+            ),
+            ImageErrorKind::Decode {
+                format,
+                chunk,
+                message,
+            } => write!(f, "invalid {} ({}): {}", format, chunk, message),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ImageErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Shorthand for building a decode error from inside a decoder.
+#[cfg(any(feature = "png", feature = "bmp", feature = "qoi"))]
+fn decode_error(format: ImageFormat, chunk: &str, message: impl Into<String>) -> ImageErrorKind {
+    ImageErrorKind::Decode {
+        format,
+        chunk: chunk.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Returns the size in bytes of an RGBA buffer for the given dimensions, if it fits in memory.
+#[cfg(any(feature = "png", feature = "bmp", feature = "qoi"))]
+fn rgba_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize)
+        .checked_mul(height as usize)?
+        .checked_mul(4)
+        .filter(|&len| len <= isize::MAX as usize)
+}
+
+/// Decode an image, detecting its format from its contents.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Image, ImageErrorKind> {
+    let format = ImageFormat::from_magic(bytes).ok_or(ImageErrorKind::UnknownFormat)?;
+
+    match format {
+        #[cfg(feature = "png")]
+        ImageFormat::Png => png::decode(bytes),
+        #[cfg(feature = "bmp")]
+        ImageFormat::Bmp => bmp::decode(bytes),
+        #[cfg(feature = "qoi")]
+        ImageFormat::Qoi => qoi::decode(bytes),
+        #[allow(unreachable_patterns)]
+        format => Err(ImageErrorKind::FormatDisabled(format)),
+    }
+}
+
+/// Encode an image as a PNG file.
+#[cfg(feature = "png")]
+pub(crate) fn encode_png(image: &Image) -> Vec<u8> {
+    png::encode(image)
+}
@@ -0,0 +1,628 @@
+use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib_with_limit};
+
+use super::{decode_error, rgba_len, ImageErrorKind, ImageFormat};
+use crate::{Color, Image};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The starting column, starting row, column step and row step of each Adam7 pass.
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// The CRC-32 of a chunk's type and data, as stored after each chunk.
+fn crc32(chunk_type: &[u8], data: &[u8]) -> u32 {
+    let crc = chunk_type
+        .iter()
+        .chain(data)
+        .fold(0xffff_ffff, |crc, &byte| {
+            CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+        });
+    crc ^ 0xffff_ffff
+}
+
+fn error(chunk: &str, message: impl Into<String>) -> ImageErrorKind {
+    decode_error(ImageFormat::Png, chunk, message)
+}
+
+/// The contents of the `IHDR` chunk.
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, ImageErrorKind> {
+        if data.len() != 13 {
+            return Err(error("IHDR", "header must be 13 bytes long"));
+        }
+
+        let header = Self {
+            width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            bit_depth: data[8],
+            color_type: data[9],
+            interlaced: match data[12] {
+                0 => false,
+                1 => true,
+                method => {
+                    return Err(error(
+                        "IHDR",
+                        format!("unknown interlace method {}", method),
+                    ))
+                }
+            },
+        };
+
+        if header.width == 0 || header.height == 0 {
+            return Err(error("IHDR", "image has no pixels"));
+        }
+
+        if data[10] != 0 || data[11] != 0 {
+            return Err(error("IHDR", "unknown compression or filter method"));
+        }
+
+        let valid_depths: &[u8] = match header.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            color_type => {
+                return Err(error("IHDR", format!("unknown color type {}", color_type)));
+            }
+        };
+        if !valid_depths.contains(&header.bit_depth) {
+            return Err(error(
+                "IHDR",
+                format!(
+                    "bit depth {} is not valid for color type {}",
+                    header.bit_depth, header.color_type
+                ),
+            ));
+        }
+
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    /// The number of bytes in a scanline of the given width, excluding the filter type byte.
+    fn stride(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// The size of the pixel data after decompression, including filter type bytes.
+    fn raw_len(&self) -> Option<usize> {
+        let scanlines = |width: u32, height: u32| {
+            if width == 0 || height == 0 {
+                Some(0)
+            } else {
+                (self.stride(width) + 1).checked_mul(height as usize)
+            }
+        };
+
+        if self.interlaced {
+            ADAM7_PASSES
+                .iter()
+                .try_fold(0usize, |total, &(x0, y0, dx, dy)| {
+                    let (width, height) = pass_size(self, x0, y0, dx, dy);
+                    total.checked_add(scanlines(width, height)?)
+                })
+        } else {
+            scanlines(self.width, self.height)
+        }
+    }
+}
+
+fn pass_size(header: &Header, x0: u32, y0: u32, dx: u32, dy: u32) -> (u32, u32) {
+    let width = header.width.saturating_sub(x0).div_ceil(dx);
+    let height = header.height.saturating_sub(y0).div_ceil(dy);
+    (width, height)
+}
+
+/// Everything needed to turn samples into colors.
+struct Decoder {
+    header: Header,
+    palette: Vec<Color>,
+    /// For grayscale and truecolor images, the raw sample values of the transparent color.
+    transparent: Option<[u16; 3]>,
+}
+
+impl Decoder {
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.header.bit_depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+            8 => row[index] as u16,
+            depth => {
+                let bit = index * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                ((row[bit / 8] >> shift) & ((1 << depth) - 1)) as u16
+            }
+        }
+    }
+
+    fn to_u8(&self, value: u16) -> u8 {
+        match self.header.bit_depth {
+            16 => ((value as u32 * 0xff + 0x7fff) / 0xffff) as u8,
+            8 => value as u8,
+            depth => (value as u32 * 0xff / ((1 << depth) - 1)) as u8,
+        }
+    }
+
+    fn pixel(&self, row: &[u8], x: usize) -> Result<Color, ImageErrorKind> {
+        let channels = self.header.channels();
+        let sample = |channel: usize| self.sample(row, x * channels + channel);
+
+        let color = match self.header.color_type {
+            0 => {
+                let gray = sample(0);
+                let value = self.to_u8(gray);
+                let alpha = if self.transparent == Some([gray, 0, 0]) {
+                    0
+                } else {
+                    0xff
+                };
+                Color::rgba(value, value, value, alpha)
+            }
+            2 => {
+                let rgb = [sample(0), sample(1), sample(2)];
+                let alpha = if self.transparent == Some(rgb) {
+                    0
+                } else {
+                    0xff
+                };
+                Color::rgba(
+                    self.to_u8(rgb[0]),
+                    self.to_u8(rgb[1]),
+                    self.to_u8(rgb[2]),
+                    alpha,
+                )
+            }
+            3 => {
+                let index = sample(0) as usize;
+                *self.palette.get(index).ok_or_else(|| {
+                    error("IDAT", format!("palette index {} is out of range", index))
+                })?
+            }
+            4 => {
+                let value = self.to_u8(sample(0));
+                Color::rgba(value, value, value, self.to_u8(sample(1)))
+            }
+            _ => Color::rgba(
+                self.to_u8(sample(0)),
+                self.to_u8(sample(1)),
+                self.to_u8(sample(2)),
+                self.to_u8(sample(3)),
+            ),
+        };
+
+        Ok(color)
+    }
+
+    /// Unfilter and convert one pass of scanlines, returning the remaining raw data.
+    fn decode_pass<'a>(
+        &self,
+        raw: &'a [u8],
+        image: &mut Image,
+        (x0, y0, dx, dy): (u32, u32, u32, u32),
+    ) -> Result<&'a [u8], ImageErrorKind> {
+        let (width, height) = pass_size(&self.header, x0, y0, dx, dy);
+        if width == 0 || height == 0 {
+            return Ok(raw);
+        }
+
+        let stride = self.header.stride(width);
+        let filter_bpp = (self.header.bits_per_pixel() / 8).max(1);
+        let mut previous = vec![0u8; stride];
+        let mut current = vec![0u8; stride];
+        let mut raw = raw;
+
+        for pass_y in 0..height {
+            let (line, rest) = raw.split_at(stride + 1);
+            raw = rest;
+
+            current.copy_from_slice(&line[1..]);
+            unfilter(line[0], &mut current, &previous, filter_bpp)?;
+
+            let y = y0 + pass_y * dy;
+            for pass_x in 0..width {
+                let x = x0 + pass_x * dx;
+                let color = self.pixel(&current, pass_x as usize)?;
+                let i = (y as usize * self.header.width as usize + x as usize) * 4;
+                image.data_mut()[i..i + 4].copy_from_slice(&color.to_array());
+            }
+
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        Ok(raw)
+    }
+}
+
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], bpp: usize) -> Result<(), ImageErrorKind> {
+    match filter {
+        0 => {}
+        1 => {
+            for i in bpp..row.len() {
+                row[i] = row[i].wrapping_add(row[i - bpp]);
+            }
+        }
+        2 => {
+            for (byte, above) in row.iter_mut().zip(previous) {
+                *byte = byte.wrapping_add(*above);
+            }
+        }
+        3 => {
+            for i in 0..row.len() {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let average = ((left as u16 + previous[i] as u16) / 2) as u8;
+                row[i] = row[i].wrapping_add(average);
+            }
+        }
+        4 => {
+            for i in 0..row.len() {
+                let (left, upper_left) = if i >= bpp {
+                    (row[i - bpp], previous[i - bpp])
+                } else {
+                    (0, 0)
+                };
+                row[i] = row[i].wrapping_add(paeth(left, previous[i], upper_left));
+            }
+        }
+        filter => {
+            return Err(error("IDAT", format!("unknown filter type {}", filter)));
+        }
+    }
+
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Image, ImageErrorKind> {
+    let mut data = bytes
+        .strip_prefix(SIGNATURE)
+        .ok_or_else(|| error("signature", "not a PNG file"))?;
+
+    let mut header: Option<Header> = None;
+    let mut palette = Vec::new();
+    let mut transparency: Option<&[u8]> = None;
+    let mut compressed = Vec::new();
+
+    loop {
+        if data.len() < 12 {
+            return Err(error("IEND", "file ended before the IEND chunk"));
+        }
+
+        let length = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let chunk_type = &data[4..8];
+        let name = String::from_utf8_lossy(chunk_type).into_owned();
+
+        if data.len() - 12 < length {
+            return Err(error(&name, "chunk is truncated"));
+        }
+
+        let body = &data[8..8 + length];
+        let crc_bytes = &data[8 + length..12 + length];
+        let crc = u32::from_be_bytes([crc_bytes[0], crc_bytes[1], crc_bytes[2], crc_bytes[3]]);
+        if crc != crc32(chunk_type, body) {
+            return Err(error(&name, "CRC mismatch, the chunk is corrupt"));
+        }
+        data = &data[12 + length..];
+
+        match chunk_type {
+            b"IHDR" => header = Some(Header::parse(body)?),
+            _ if header.is_none() => {
+                return Err(error(&name, "chunk appears before IHDR"));
+            }
+            b"PLTE" => {
+                if body.len() % 3 != 0 || body.len() > 256 * 3 {
+                    return Err(error(&name, "palette has an invalid length"));
+                }
+                palette = body
+                    .chunks_exact(3)
+                    .map(|rgb| Color::rgb(rgb[0], rgb[1], rgb[2]))
+                    .collect();
+            }
+            b"tRNS" => transparency = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // Critical chunks have an uppercase first letter, and can't be safely ignored
+            _ if chunk_type[0].is_ascii_uppercase() => {
+                return Err(error(&name, "unsupported critical chunk"));
+            }
+            _ => {}
+        }
+    }
+
+    let Some(header) = header else {
+        return Err(error("IHDR", "missing image header"));
+    };
+
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(error("PLTE", "missing palette for an indexed color image"));
+    }
+
+    let mut transparent = None;
+    if let Some(trns) = transparency {
+        let value = |i: usize| -> Result<u16, ImageErrorKind> {
+            trns.get(i * 2..i * 2 + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or_else(|| error("tRNS", "transparency chunk is too short"))
+        };
+
+        match header.color_type {
+            0 => transparent = Some([value(0)?, 0, 0]),
+            2 => transparent = Some([value(0)?, value(1)?, value(2)?]),
+            3 => {
+                if trns.len() > palette.len() {
+                    return Err(error("tRNS", "more alpha values than palette entries"));
+                }
+                for (color, &alpha) in palette.iter_mut().zip(trns) {
+                    color.a = alpha;
+                }
+            }
+            _ => {
+                return Err(error(
+                    "tRNS",
+                    "not allowed for images with an alpha channel",
+                ))
+            }
+        }
+    }
+
+    if compressed.is_empty() {
+        return Err(error("IDAT", "missing image data"));
+    }
+
+    let (Some(raw_len), Some(_)) = (header.raw_len(), rgba_len(header.width, header.height)) else {
+        return Err(error("IHDR", "image is too large"));
+    };
+
+    let raw = decompress_to_vec_zlib_with_limit(&compressed, raw_len)
+        .map_err(|e| error("IDAT", format!("corrupt compressed data ({:?})", e.status)))?;
+    if raw.len() < raw_len {
+        return Err(error("IDAT", "image data is truncated"));
+    }
+
+    let decoder = Decoder {
+        palette,
+        transparent,
+        header,
+    };
+    let mut image = Image::new(decoder.header.width, decoder.header.height);
+
+    if decoder.header.interlaced {
+        let mut remaining = raw.as_slice();
+        for pass in ADAM7_PASSES {
+            remaining = decoder.decode_pass(remaining, &mut image, pass)?;
+        }
+    } else {
+        decoder.decode_pass(&raw, &mut image, (0, 0, 1, 1))?;
+    }
+
+    Ok(image)
+}
+
+/// Encode an image as an 8-bit RGBA PNG.
+pub(crate) fn encode(image: &Image) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    // 8 bits per channel, RGBA, default compression and filtering, not interlaced
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each row is stored unfiltered, which compresses well enough for flat pixel art
+    let stride = image.width() as usize * 4;
+    let mut raw = Vec::with_capacity((stride + 1) * image.height() as usize);
+    for row in image.data().chunks_exact(stride.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &compress_to_vec_zlib(&raw, 6));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc32(chunk_type, data).to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a PNG from a header, any extra chunks before the image data, and raw scanlines.
+    fn build(header: [u8; 13], chunks: &[(&[u8; 4], &[u8])], raw: &[u8]) -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        for (chunk_type, data) in chunks {
+            write_chunk(&mut png, chunk_type, data);
+        }
+        write_chunk(&mut png, b"IDAT", &compress_to_vec_zlib(raw, 6));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    fn header(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> [u8; 13] {
+        let mut header = [0; 13];
+        header[0..4].copy_from_slice(&width.to_be_bytes());
+        header[4..8].copy_from_slice(&height.to_be_bytes());
+        header[8] = bit_depth;
+        header[9] = color_type;
+        header[12] = interlace;
+        header
+    }
+
+    fn decode_error(bytes: &[u8]) -> (String, String) {
+        match decode(bytes) {
+            Err(ImageErrorKind::Decode { chunk, message, .. }) => (chunk, message),
+            Err(error) => panic!("unexpected error {:?}", error),
+            Ok(_) => panic!("decoded an invalid PNG"),
+        }
+    }
+
+    fn pixel(image: &Image, x: i32, y: i32) -> Color {
+        image.get_pixel(x, y).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_the_encoder() {
+        let mut image = Image::new(3, 2);
+        for (i, byte) in image.data_mut().iter_mut().enumerate() {
+            *byte = (i * 10) as u8;
+        }
+
+        let decoded = decode(&encode(&image)).unwrap();
+        assert_eq!(decoded.width(), 3);
+        assert_eq!(decoded.height(), 2);
+        assert_eq!(decoded.data(), image.data());
+    }
+
+    #[test]
+    fn decodes_paletted_images() {
+        let palette = [0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff];
+        // Two bits per pixel, with the first entry half transparent
+        let raw = [0, 0b0001_0000, 0, 0b1001_0000];
+        let png = build(
+            header(2, 2, 2, 3, 0),
+            &[(b"PLTE", &palette), (b"tRNS", &[0x80])],
+            &raw,
+        );
+
+        let image = decode(&png).unwrap();
+        assert_eq!(pixel(&image, 0, 0), Color::rgba(0xff, 0x00, 0x00, 0x80));
+        assert_eq!(pixel(&image, 1, 0), Color::rgb(0x00, 0xff, 0x00));
+        assert_eq!(pixel(&image, 0, 1), Color::rgb(0x00, 0x00, 0xff));
+        assert_eq!(pixel(&image, 1, 1), Color::rgb(0x00, 0xff, 0x00));
+    }
+
+    #[test]
+    fn decodes_16_bit_images() {
+        // The second row uses the up filter, so is stored as the difference from the first
+        let raw = [
+            0, 0xff, 0xff, 0x80, 0x00, 0x00, 0x00, //
+            2, 0x01, 0x01, 0x00, 0x00, 0xff, 0xff,
+        ];
+        let png = build(header(1, 2, 16, 2, 0), &[], &raw);
+
+        let image = decode(&png).unwrap();
+        assert_eq!(pixel(&image, 0, 0), Color::rgb(0xff, 0x80, 0x00));
+        assert_eq!(pixel(&image, 0, 1), Color::rgb(0x00, 0x80, 0xff));
+    }
+
+    #[test]
+    fn decodes_interlaced_images() {
+        let (width, height) = (5, 5);
+        let value = |x: u32, y: u32| (y * width + x) as u8 * 10;
+
+        let mut raw = Vec::new();
+        for (x0, y0, dx, dy) in ADAM7_PASSES {
+            for y in (y0..height).step_by(dy as usize) {
+                if x0 >= width {
+                    continue;
+                }
+                raw.push(0);
+                raw.extend((x0..width).step_by(dx as usize).map(|x| value(x, y)));
+            }
+        }
+        let png = build(header(width, height, 8, 0, 1), &[], &raw);
+
+        let image = decode(&png).unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                let gray = value(x, y);
+                assert_eq!(
+                    pixel(&image, x as i32, y as i32),
+                    Color::rgb(gray, gray, gray)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_a_bad_crc() {
+        let mut png = encode(&Image::new(2, 2));
+        // The first byte of the width in IHDR
+        png[16] ^= 0xff;
+
+        let (chunk, message) = decode_error(&png);
+        assert_eq!(chunk, "IHDR");
+        assert!(message.contains("CRC"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_truncated_image_data() {
+        // Only the first of two rows
+        let png = build(header(2, 2, 8, 6, 0), &[], &[0; 9]);
+        assert_eq!(
+            decode_error(&png),
+            ("IDAT".to_string(), "image data is truncated".to_string())
+        );
+
+        // The file ends part way through the IDAT chunk, which is followed by the 12 byte IEND
+        let png = encode(&Image::new(2, 2));
+        assert_eq!(
+            decode_error(&png[..png.len() - 20]),
+            ("IDAT".to_string(), "chunk is truncated".to_string())
+        );
+    }
+}
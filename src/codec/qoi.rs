@@ -0,0 +1,196 @@
+use super::{decode_error, rgba_len, ImageErrorKind, ImageFormat};
+use crate::Image;
+
+const HEADER_SIZE: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+/// The largest image the reference implementation will decode.
+const MAX_PIXELS: u64 = 400_000_000;
+
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const OP_INDEX: u8 = 0b00;
+const OP_DIFF: u8 = 0b01;
+const OP_LUMA: u8 = 0b10;
+
+fn error(chunk: &str, message: impl Into<String>) -> ImageErrorKind {
+    decode_error(ImageFormat::Qoi, chunk, message)
+}
+
+fn hash([r, g, b, a]: [u8; 4]) -> usize {
+    (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Image, ImageErrorKind> {
+    if bytes.len() < HEADER_SIZE || !bytes.starts_with(b"qoif") {
+        return Err(error("header", "not a QOI file"));
+    }
+
+    let width = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    let height = u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    let channels = bytes[12];
+    let colorspace = bytes[13];
+
+    if width == 0 || height == 0 {
+        return Err(error("header", "image has no pixels"));
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(error("header", "image is too large"));
+    }
+    if channels != 3 && channels != 4 {
+        return Err(error(
+            "header",
+            format!("invalid channel count {}", channels),
+        ));
+    }
+    if colorspace > 1 {
+        return Err(error(
+            "header",
+            format!("invalid colorspace {}", colorspace),
+        ));
+    }
+
+    let len = rgba_len(width, height).ok_or_else(|| error("header", "image is too large"))?;
+    // A corrupt header can claim far more pixels than the data holds, and no op encodes more
+    // than a run of 62 pixels
+    let mut data = Vec::with_capacity(len.min((bytes.len() - HEADER_SIZE).saturating_mul(62 * 4)));
+
+    let mut index = [[0u8; 4]; 64];
+    let mut pixel = [0, 0, 0, 0xff];
+    let mut run = 0;
+    let mut pos = HEADER_SIZE;
+
+    while data.len() < len {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let chunk_start = pos;
+            let mut next = || {
+                let byte = bytes.get(pos).copied();
+                pos += 1;
+                byte.ok_or_else(|| {
+                    error(
+                        &format!("chunk at byte {}", chunk_start),
+                        "unexpected end of data",
+                    )
+                })
+            };
+
+            let tag = next()?;
+            match tag {
+                OP_RGB => {
+                    pixel[0] = next()?;
+                    pixel[1] = next()?;
+                    pixel[2] = next()?;
+                }
+                OP_RGBA => {
+                    pixel[0] = next()?;
+                    pixel[1] = next()?;
+                    pixel[2] = next()?;
+                    pixel[3] = next()?;
+                }
+                _ => match tag >> 6 {
+                    OP_INDEX => pixel = index[(tag & 0x3f) as usize],
+                    OP_DIFF => {
+                        pixel[0] = pixel[0].wrapping_add((tag >> 4) & 0x03).wrapping_sub(2);
+                        pixel[1] = pixel[1].wrapping_add((tag >> 2) & 0x03).wrapping_sub(2);
+                        pixel[2] = pixel[2].wrapping_add(tag & 0x03).wrapping_sub(2);
+                    }
+                    OP_LUMA => {
+                        let second = next()?;
+                        let dg = (tag & 0x3f).wrapping_sub(32);
+                        pixel[0] = pixel[0]
+                            .wrapping_add(dg)
+                            .wrapping_add(second >> 4)
+                            .wrapping_sub(8);
+                        pixel[1] = pixel[1].wrapping_add(dg);
+                        pixel[2] = pixel[2]
+                            .wrapping_add(dg)
+                            .wrapping_add(second & 0x0f)
+                            .wrapping_sub(8);
+                    }
+                    // OP_RUN, where the run length is stored with a bias of -1
+                    _ => run = tag & 0x3f,
+                },
+            }
+
+            index[hash(pixel)] = pixel;
+        }
+
+        data.extend_from_slice(&pixel);
+    }
+
+    if !bytes[pos..].starts_with(&END_MARKER) {
+        return Err(error(
+            &format!("end marker at byte {}", pos),
+            "missing end marker",
+        ));
+    }
+
+    Ok(Image::from_rgba(width, height, data).expect("QOI decoder produced a full image"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32) -> Vec<u8> {
+        let mut qoi = b"qoif".to_vec();
+        qoi.extend_from_slice(&width.to_be_bytes());
+        qoi.extend_from_slice(&height.to_be_bytes());
+        qoi.extend_from_slice(&[4, 0]);
+        qoi
+    }
+
+    #[test]
+    fn decodes_every_op() {
+        let mut qoi = header(8, 1);
+        qoi.extend_from_slice(&[OP_RGB, 10, 20, 30]);
+        // DIFF of +1, -1, 0
+        qoi.push(0b01_11_01_10);
+        // LUMA with a green difference of +5, and red and blue -2 and +3 from that
+        qoi.extend_from_slice(&[0b10_100101, 0x6b]);
+        qoi.extend_from_slice(&[OP_RGBA, 1, 2, 3, 128]);
+        // INDEX of the first pixel
+        qoi.push(0b00_001001);
+        // RUN of 3
+        qoi.push(0b11_000010);
+        qoi.extend_from_slice(&END_MARKER);
+
+        let image = decode(&qoi).unwrap();
+        let first = [10, 20, 30, 255];
+        let expected = [
+            first,
+            [11, 19, 30, 255],
+            [14, 24, 38, 255],
+            [1, 2, 3, 128],
+            first,
+            first,
+            first,
+            first,
+        ];
+        assert_eq!(image.data(), expected.concat());
+    }
+
+    #[test]
+    fn rejects_missing_data_for_a_large_header() {
+        let mut qoi = header(20_000, 20_000);
+        qoi.extend_from_slice(&[OP_RGB, 1, 2, 3]);
+
+        match decode(&qoi) {
+            Err(ImageErrorKind::Decode { message, .. }) => {
+                assert_eq!(message, "unexpected end of data")
+            }
+            Err(error) => panic!("unexpected error {:?}", error),
+            Ok(_) => panic!("decoded a truncated QOI"),
+        }
+    }
+
+    #[test]
+    fn rejects_a_missing_end_marker() {
+        let mut qoi = header(1, 1);
+        qoi.extend_from_slice(&[OP_RGB, 1, 2, 3]);
+
+        assert!(matches!(decode(&qoi), Err(ImageErrorKind::Decode { .. })));
+    }
+}
@@ -0,0 +1,138 @@
+/// An RGBA color, with 8 bits per channel.
+/// This matches the layout of a pixel in the frame buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(0xff, 0xff, 0xff);
+    pub const GRAY: Color = Color::rgb(0x80, 0x80, 0x80);
+    pub const RED: Color = Color::rgb(0xff, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 0xff, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 0xff);
+    pub const YELLOW: Color = Color::rgb(0xff, 0xff, 0);
+    pub const CYAN: Color = Color::rgb(0, 0xff, 0xff);
+    pub const MAGENTA: Color = Color::rgb(0xff, 0, 0xff);
+
+    /// Create a fully opaque color.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xff }
+    }
+
+    /// Create a color with the given alpha.
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Create a fully opaque color from a `0xRRGGBB` value.
+    pub const fn hex(rgb: u32) -> Self {
+        Self::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    /// Returns the same color with a different alpha.
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Returns the color as `[r, g, b, a]`, as stored in the frame buffer.
+    pub const fn to_array(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// Create a color from `[r, g, b, a]`, as stored in the frame buffer.
+    pub const fn from_array(rgba: [u8; 4]) -> Self {
+        Self::rgba(rgba[0], rgba[1], rgba[2], rgba[3])
+    }
+
+    /// Multiply each channel of this color by another color, treating channels as 0 to 1.
+    pub fn multiply(self, other: Color) -> Color {
+        let channel = |a: u8, b: u8| ((a as u32 * b as u32 + 0x7f) / 0xff) as u8;
+
+        Color::rgba(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            channel(self.a, other.a),
+        )
+    }
+
+    /// Composite this color over `dst`, using the Porter-Duff "over" operator.
+    /// Both colors use straight (non-premultiplied) alpha.
+    pub fn over(self, dst: Color) -> Color {
+        match (self.a, dst.a) {
+            (0xff, _) | (_, 0) => return self,
+            (0, _) => return dst,
+            _ => {}
+        }
+
+        // Everything is scaled by 255 * 255 to stay in integer arithmetic
+        let src_a = self.a as u32 * 0xff;
+        let dst_a = dst.a as u32 * (0xff - self.a as u32);
+        let out_a = src_a + dst_a;
+
+        let channel =
+            |s: u8, d: u8| ((s as u32 * src_a + d as u32 * dst_a + out_a / 2) / out_a) as u8;
+
+        Color::rgba(
+            channel(self.r, dst.r),
+            channel(self.g, dst.g),
+            channel(self.b, dst.b),
+            ((out_a + 0x7f) / 0xff) as u8,
+        )
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from(rgba: [u8; 4]) -> Self {
+        Self::from_array(rgba)
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn over_blends_straight_alpha() {
+        let half_red = Color::rgba(0xff, 0, 0, 0x80);
+
+        assert_eq!(half_red.over(Color::BLUE), Color::rgba(0x80, 0, 0x7f, 0xff));
+        assert_eq!(
+            half_red.over(Color::BLUE.with_alpha(0x80)),
+            Color::rgba(0xaa, 0, 0x55, 0xc0)
+        );
+    }
+
+    #[test]
+    fn over_shortcuts_opaque_and_transparent_colors() {
+        let dst = Color::rgba(10, 20, 30, 40);
+
+        assert_eq!(Color::RED.over(dst), Color::RED);
+        assert_eq!(Color::rgba(1, 2, 3, 0).over(dst), dst);
+        assert_eq!(
+            Color::RED.with_alpha(0x10).over(Color::TRANSPARENT),
+            Color::RED.with_alpha(0x10)
+        );
+    }
+
+    #[test]
+    fn multiply_treats_channels_as_fractions() {
+        assert_eq!(Color::WHITE.multiply(Color::CYAN), Color::CYAN);
+        assert_eq!(
+            Color::GRAY.multiply(Color::GRAY.with_alpha(0x80)),
+            Color::rgba(0x40, 0x40, 0x40, 0x80)
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Catching panics in the app, and saving a crash report for bug reports.
+
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::Once,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{BitmapFont, Canvas, Color, FrameStats, RenderContext, TextOptions};
+
+/// Details of a panic, captured by the panic hook while the stack is still intact.
+struct PanicDetails {
+    location: Option<String>,
+    backtrace: String,
+}
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<PanicDetails>> = const { RefCell::new(None) };
+}
+
+/// Install a panic hook that captures a backtrace for the crash report.
+/// The previous hook still runs, so the panic is printed as usual.
+pub(crate) fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let details = PanicDetails {
+                location: info.location().map(|location| location.to_string()),
+                backtrace: Backtrace::force_capture().to_string(),
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(details));
+            previous(info);
+        }));
+    });
+}
+
+/// A report of a caught panic.
+pub(crate) struct CrashReport {
+    message: String,
+    location: Option<String>,
+    backtrace: String,
+    app_version: Option<String>,
+    stats: FrameStats,
+}
+
+impl CrashReport {
+    /// Build a report from the payload of a caught panic.
+    pub(crate) fn new(
+        payload: &(dyn Any + Send),
+        app_version: Option<&str>,
+        ctx: &RenderContext,
+    ) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let details = LAST_PANIC.with(|last| last.borrow_mut().take());
+
+        Self {
+            message,
+            location: details
+                .as_ref()
+                .and_then(|details| details.location.clone()),
+            backtrace: details
+                .map_or_else(|| "unavailable".to_string(), |details| details.backtrace),
+            app_version: app_version.map(str::to_string),
+            stats: ctx.frame_stats().clone(),
+        }
+    }
+
+    /// Write the report to `crash-<timestamp>.txt` in the directory, and the frame buffer to
+    /// `crash-<timestamp>.png` next to it. The frame buffer isn't restored to the last presented
+    /// frame, so may be partly drawn.
+    /// Returns the path of the report.
+    pub(crate) fn save(&self, dir: &Path, ctx: &RenderContext) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).with_context(|| {
+            format!("Failed to create crash report directory {}", dir.display())
+        })?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = dir.join(format!("crash-{}.txt", timestamp));
+
+        let mut text = self.to_text(timestamp);
+
+        #[cfg(feature = "png")]
+        {
+            let frame_path = path.with_extension("png");
+            let frame = crate::Image::from_rgba(
+                ctx.frame_width(),
+                ctx.frame_height(),
+                ctx.frame().to_vec(),
+            )?;
+            frame.save_png(&frame_path)?;
+            let _ = writeln!(text, "\nFrame: {}", frame_path.display());
+        }
+        #[cfg(not(feature = "png"))]
+        {
+            let _ = ctx;
+            let _ = writeln!(text, "\nFrame: not saved, the `png` feature is disabled");
+        }
+
+        std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write crash report {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    fn to_text(&self, timestamp: u128) -> String {
+        let mut text = String::new();
+
+        // Writing to a String can't fail
+        let _ = writeln!(text, "Crash report");
+        let _ = writeln!(text, "Time: {} ms since the Unix epoch", timestamp);
+        let _ = writeln!(
+            text,
+            "App version: {}",
+            self.app_version.as_deref().unwrap_or("unknown")
+        );
+        let _ = writeln!(text, "Perovskite version: {}", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(
+            text,
+            "OS: {} {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        let _ = writeln!(text, "\nPanic: {}", self.message);
+        let _ = writeln!(
+            text,
+            "Location: {}",
+            self.location.as_deref().unwrap_or("unknown")
+        );
+
+        let stats = &self.stats;
+        let _ = writeln!(text, "\nFrames drawn: {}", stats.frame_count());
+        let _ = writeln!(text, "FPS: {:.1}", stats.fps());
+        let _ = writeln!(text, "Last frame time: {:?}", stats.frame_time());
+        let _ = writeln!(
+            text,
+            "Last frame: {} updates in {:?}, drawn in {:?}, presented in {:?}",
+            stats.updates(),
+            stats.update_time(),
+            stats.draw_time(),
+            stats.present_time()
+        );
+        let history: Vec<String> = stats
+            .history()
+            .map(|frame_time| format!("{:.1}", frame_time.as_secs_f32() * 1000.0))
+            .collect();
+        let _ = writeln!(text, "Recent frame times (ms): {}", history.join(" "));
+
+        let _ = writeln!(text, "\nBacktrace:\n{}", self.backtrace.trim_end());
+
+        text
+    }
+
+    /// Draw a simple error screen over the frame.
+    /// `report_path` is the saved report, or the error from saving it.
+    pub(crate) fn draw_screen(&self, canvas: &mut Canvas, report_path: &Result<PathBuf>) {
+        const PADDING: i32 = 8;
+
+        canvas.clear(Color::rgb(0x40, 0x00, 0x00));
+
+        let report = match report_path {
+            Ok(path) => format!("A crash report was saved to {}", path.display()),
+            Err(error) => format!("The crash report could not be saved: {}", error),
+        };
+        let text = format!(
+            "The app has crashed.\n\n{}\n\n{}\n\nClose the window to exit.",
+            self.message, report
+        );
+
+        let options = TextOptions::new(Color::WHITE)
+            .with_max_width((canvas.width() as i32 - PADDING * 2).max(1) as u32);
+        canvas.draw_text_with(
+            BitmapFont::default_font(),
+            PADDING,
+            PADDING,
+            &text,
+            &options,
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::{App, RenderContext};
+
+/// The part of the app that returned an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorPhase {
+    /// `App::update`, `App::on_timer` or `App::on_lag`.
+    Update,
+    /// `App::draw`, including presenting the frame.
+    Draw,
+    /// Resizing the frame buffer's surface, or `App::on_resize`.
+    Resize,
+    /// `App::handle_event`, or any other hook called in response to a window event.
+    Event,
+    /// `App::on_exit`, or saving the input recording.
+    Exit,
+}
+
+impl fmt::Display for ErrorPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorPhase::Update => "update",
+            ErrorPhase::Draw => "draw",
+            ErrorPhase::Resize => "resize",
+            ErrorPhase::Event => "event",
+            ErrorPhase::Exit => "exit",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What to do after `App::on_error` has handled an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorAction {
+    /// Carry on as if the call had succeeded.
+    Continue,
+    /// Skip the rest of the frame, or of the event being handled.
+    /// The next frame runs as normal, and any simulation time still owed is caught up on.
+    SkipFrame,
+    /// Exit the app, after calling `App::on_exit`.
+    Exit,
+}
+
+/// An error returned by the app, and the phase it was returned from.
+#[derive(Debug)]
+pub struct AppError {
+    phase: ErrorPhase,
+    error: anyhow::Error,
+}
+
+impl AppError {
+    pub fn new(phase: ErrorPhase, error: anyhow::Error) -> Self {
+        Self { phase, error }
+    }
+
+    /// The phase that failed.
+    pub fn phase(&self) -> ErrorPhase {
+        self.phase
+    }
+
+    /// The error that was returned.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    /// Take the error that was returned, discarding the phase.
+    pub fn into_inner(self) -> anyhow::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error during {}: {}", self.phase, self.error)
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Let the app decide what to do about the result of a call.
+/// Returns the error if the app should exit.
+pub(crate) fn handle_error<A: App>(
+    app: &mut A,
+    ctx: &mut RenderContext,
+    phase: ErrorPhase,
+    result: Result<()>,
+) -> Result<ErrorAction, AppError> {
+    let Err(error) = result else {
+        return Ok(ErrorAction::Continue);
+    };
+
+    let error = AppError::new(phase, error);
+    match app.on_error(ctx, &error) {
+        ErrorAction::Exit => Err(error),
+        action => Ok(action),
+    }
+}
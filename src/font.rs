@@ -0,0 +1,615 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::OnceLock,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{Color, Image, Rect};
+
+/// Horizontal alignment of each line of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Options for drawing text with `Canvas::draw_text_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextOptions {
+    /// The color of the text. Glyphs are tinted with this color.
+    pub color: Color,
+    /// If set, lines are wrapped at word boundaries to fit within this width.
+    pub max_width: Option<u32>,
+    /// How lines are aligned within the text block.
+    /// The block is `max_width` wide if set, otherwise as wide as the longest line.
+    pub align: TextAlign,
+}
+
+impl TextOptions {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            max_width: None,
+            align: TextAlign::Left,
+        }
+    }
+
+    pub fn with_max_width(mut self, max_width: u32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self::new(Color::WHITE)
+    }
+}
+
+/// A single character in a bitmap font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    /// The index of the page image containing the glyph.
+    pub page: usize,
+    /// The region of the page image containing the glyph.
+    pub source: Rect,
+    /// The offset from the pen position to draw the glyph at.
+    pub offset_x: i32,
+    /// The offset from the top of the line to draw the glyph at.
+    pub offset_y: i32,
+    /// How far to move the pen after drawing the glyph.
+    pub advance: i32,
+}
+
+/// A glyph positioned by `BitmapFont::layout`, relative to the top left of the text block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionedGlyph {
+    pub glyph: Glyph,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A font made of glyphs stored in one or more page images, in the style of an XNA SpriteFont.
+/// Page images are expected to be white glyphs on a transparent background, so they can be
+/// tinted to any color.
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    line_height: u32,
+    base: u32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i32>,
+    pages: Vec<Image>,
+}
+
+impl BitmapFont {
+    /// Create a font from its parts.
+    pub fn new(line_height: u32, base: u32, pages: Vec<Image>) -> Self {
+        Self {
+            line_height,
+            base,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+            pages,
+        }
+    }
+
+    /// The built-in 8x8 monospaced font, covering printable ASCII.
+    pub fn default_font() -> &'static BitmapFont {
+        static FONT: OnceLock<BitmapFont> = OnceLock::new();
+        FONT.get_or_init(build_default_font)
+    }
+
+    /// Load an AngelCode BMFont, from a text format `.fnt` file.
+    /// Page images are loaded relative to the `.fnt` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read font {}", path.display()))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        Self::parse_bmfont(&source, |file| Ok(Image::load(directory.join(file))?))
+            .with_context(|| format!("Failed to load font {}", path.display()))
+    }
+
+    /// Create a font from the contents of a text format BMFont `.fnt` file, and its page images.
+    /// The pages must be in the same order as the page IDs in the file.
+    pub fn from_bmfont(source: &str, pages: Vec<Image>) -> Result<Self> {
+        let mut pages = pages.into_iter();
+        Self::parse_bmfont(source, |file| {
+            pages
+                .next()
+                .ok_or_else(|| anyhow!("Missing image for page {}", file))
+        })
+    }
+
+    fn parse_bmfont(
+        source: &str,
+        mut load_page: impl FnMut(&str) -> Result<Image>,
+    ) -> Result<Self> {
+        let mut font = BitmapFont::new(0, 0, Vec::new());
+        // Filled as `page` lines are read, so a huge page count in the header can't allocate
+        let mut page_count = 0;
+        let mut page_files = BTreeMap::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let (tag, attributes) = parse_bmfont_line(line);
+            let attribute = |name: &str| -> Result<i32> {
+                let value = attributes
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.as_str())
+                    .ok_or_else(|| anyhow!("Missing `{}` on line {}", name, number + 1))?;
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid `{}` on line {}", name, number + 1))
+            };
+
+            match tag {
+                "common" => {
+                    font.line_height = attribute("lineHeight")?.max(0) as u32;
+                    font.base = attribute("base")?.max(0) as u32;
+                    page_count = attribute("pages")?.max(0) as usize;
+                }
+                "page" => {
+                    let id = attribute("id")?;
+                    let id = usize::try_from(id)
+                        .ok()
+                        .filter(|&id| id < page_count)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Page {} on line {} is out of range, the font has {} pages",
+                                id,
+                                number + 1,
+                                page_count
+                            )
+                        })?;
+                    let file = attributes
+                        .iter()
+                        .find(|(key, _)| *key == "file")
+                        .map(|(_, value)| value.clone())
+                        .ok_or_else(|| anyhow!("Missing `file` on line {}", number + 1))?;
+                    page_files.insert(id, file);
+                }
+                "char" => {
+                    let id = attribute("id")?;
+                    let Some(character) = char::from_u32(id as u32) else {
+                        continue;
+                    };
+                    font.glyphs.insert(
+                        character,
+                        Glyph {
+                            page: attribute("page").unwrap_or(0).max(0) as usize,
+                            source: Rect::new(
+                                attribute("x")?,
+                                attribute("y")?,
+                                attribute("width")?.max(0) as u32,
+                                attribute("height")?.max(0) as u32,
+                            ),
+                            offset_x: attribute("xoffset")?,
+                            offset_y: attribute("yoffset")?,
+                            advance: attribute("xadvance")?,
+                        },
+                    );
+                }
+                "kerning" => {
+                    let first = char::from_u32(attribute("first")? as u32);
+                    let second = char::from_u32(attribute("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        font.kerning.insert((first, second), attribute("amount")?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if font.line_height == 0 {
+            bail!("Missing `common` line");
+        }
+
+        for id in 0..page_count {
+            let Some(file) = page_files.get(&id) else {
+                bail!("Missing `page` line for page {}", id);
+            };
+            font.pages.push(load_page(file)?);
+        }
+
+        if let Some(glyph) = font.glyphs.values().find(|g| g.page >= font.pages.len()) {
+            bail!("Glyph refers to missing page {}", glyph.page);
+        }
+
+        Ok(font)
+    }
+
+    /// Add or replace a glyph.
+    pub fn insert_glyph(&mut self, character: char, glyph: Glyph) {
+        self.glyphs.insert(character, glyph);
+    }
+
+    /// Set the kerning adjustment between a pair of characters.
+    pub fn set_kerning(&mut self, first: char, second: char, amount: i32) {
+        self.kerning.insert((first, second), amount);
+    }
+
+    /// The distance between the top of each line, in pixels
+    pub fn line_height(&self) -> u32 {
+        self.line_height
+    }
+
+    /// The distance from the top of a line to the baseline, in pixels
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Get the page images of the font
+    pub fn pages(&self) -> &[Image] {
+        &self.pages
+    }
+
+    /// Get the glyph for a character.
+    /// Falls back to `?` for characters that are not in the font.
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// Get the kerning adjustment between a pair of characters
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    /// Measure the width of a single line of text, in pixels.
+    pub fn line_width(&self, line: &str) -> u32 {
+        let mut width = 0;
+        let mut previous = None;
+
+        for character in line.chars() {
+            if let Some(previous) = previous {
+                width += self.kerning(previous, character);
+            }
+            width += self.glyph(character).map_or(0, |g| g.advance);
+            previous = Some(character);
+        }
+
+        width.max(0) as u32
+    }
+
+    /// Split text into lines, on newlines and by wrapping words to fit within `max_width`.
+    /// Words that are too long to fit on a line by themselves are broken between characters.
+    pub fn wrap(&self, text: &str, max_width: Option<u32>) -> Vec<String> {
+        wrap_text(text, max_width, |line| self.line_width(line))
+    }
+
+    /// Measure the size of a block of text, in pixels.
+    pub fn measure(&self, text: &str, max_width: Option<u32>) -> (u32, u32) {
+        let lines = self.wrap(text, max_width);
+        let width = lines
+            .iter()
+            .map(|line| self.line_width(line))
+            .max()
+            .unwrap_or(0);
+
+        (width, lines.len() as u32 * self.line_height)
+    }
+
+    /// Position each glyph of a block of text, relative to its top left corner.
+    pub fn layout(&self, text: &str, options: &TextOptions) -> Vec<PositionedGlyph> {
+        let lines = self.wrap(text, options.max_width);
+        let widths: Vec<u32> = lines.iter().map(|line| self.line_width(line)).collect();
+        let block_width = options
+            .max_width
+            .unwrap_or_else(|| widths.iter().copied().max().unwrap_or(0));
+
+        let mut glyphs = Vec::new();
+        for (index, (line, width)) in lines.iter().zip(widths).enumerate() {
+            let mut x = align_offset(options.align, block_width, width);
+            let y = index as i32 * self.line_height as i32;
+
+            let mut previous = None;
+            for character in line.chars() {
+                if let Some(previous) = previous {
+                    x += self.kerning(previous, character);
+                }
+                previous = Some(character);
+
+                let Some(glyph) = self.glyph(character) else {
+                    continue;
+                };
+
+                if !glyph.source.is_empty() {
+                    glyphs.push(PositionedGlyph {
+                        glyph: *glyph,
+                        x: x + glyph.offset_x,
+                        y: y + glyph.offset_y,
+                    });
+                }
+                x += glyph.advance;
+            }
+        }
+
+        glyphs
+    }
+}
+
+/// Split text into lines, on newlines and by wrapping words to fit within `max_width`, using
+/// `line_width` to measure candidate lines.
+pub(crate) fn wrap_text(
+    text: &str,
+    max_width: Option<u32>,
+    line_width: impl Fn(&str) -> u32,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let Some(max_width) = max_width else {
+            lines.push(paragraph.to_string());
+            continue;
+        };
+
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+
+            if line_width(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            for character in word.chars() {
+                line.push(character);
+                if line.chars().count() > 1 && line_width(&line) > max_width {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, character.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Horizontal offset of a line of the given width within a block, for an alignment.
+pub(crate) fn align_offset(align: TextAlign, block_width: u32, line_width: u32) -> i32 {
+    match align {
+        TextAlign::Left => 0,
+        TextAlign::Center => (block_width as i32 - line_width as i32) / 2,
+        TextAlign::Right => block_width as i32 - line_width as i32,
+    }
+}
+
+/// Split a BMFont line into its tag and `key=value` attributes.
+/// Values may be quoted, in which case they can contain spaces.
+fn parse_bmfont_line(line: &str) -> (&str, Vec<(&str, String)>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut attributes = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            quoted.split_once('"').unwrap_or((quoted, ""))
+        } else {
+            after.split_once(' ').unwrap_or((after, ""))
+        };
+
+        attributes.push((key.trim(), value.to_string()));
+        rest = after;
+    }
+
+    (tag, attributes)
+}
+
+const DEFAULT_GLYPH_SIZE: u32 = 8;
+const DEFAULT_GLYPHS_PER_ROW: u32 = 16;
+
+/// Render the built-in glyph bitmaps into a page image.
+fn build_default_font() -> BitmapFont {
+    let rows = (DEFAULT_GLYPHS.len() as u32).div_ceil(DEFAULT_GLYPHS_PER_ROW);
+    let mut page = Image::new(
+        DEFAULT_GLYPHS_PER_ROW * DEFAULT_GLYPH_SIZE,
+        rows * DEFAULT_GLYPH_SIZE,
+    );
+
+    let mut glyphs = HashMap::new();
+    {
+        let mut canvas = page.canvas();
+
+        for (index, bitmap) in DEFAULT_GLYPHS.iter().enumerate() {
+            let index = index as u32;
+            let source = Rect::new(
+                ((index % DEFAULT_GLYPHS_PER_ROW) * DEFAULT_GLYPH_SIZE) as i32,
+                ((index / DEFAULT_GLYPHS_PER_ROW) * DEFAULT_GLYPH_SIZE) as i32,
+                DEFAULT_GLYPH_SIZE,
+                DEFAULT_GLYPH_SIZE,
+            );
+
+            // The least significant bit of each row is the leftmost pixel
+            for (y, row) in bitmap.iter().enumerate() {
+                for x in 0..DEFAULT_GLYPH_SIZE as i32 {
+                    if row & (1 << x) != 0 {
+                        canvas.set_pixel(source.x + x, source.y + y as i32, Color::WHITE);
+                    }
+                }
+            }
+
+            let character = char::from(b' ' + index as u8);
+            glyphs.insert(
+                character,
+                Glyph {
+                    page: 0,
+                    source,
+                    offset_x: 0,
+                    offset_y: 0,
+                    advance: DEFAULT_GLYPH_SIZE as i32,
+                },
+            );
+        }
+    }
+
+    BitmapFont {
+        line_height: DEFAULT_GLYPH_SIZE,
+        base: DEFAULT_GLYPH_SIZE - 1,
+        glyphs,
+        kerning: HashMap::new(),
+        pages: vec![page],
+    }
+}
+
+/// An 8x8 font covering printable ASCII (`' '` to `'~'`), one byte per row.
+/// Based on the public domain font8x8 by Daniel Hepper.
+#[rustfmt::skip]
+const DEFAULT_GLYPHS: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // !
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // #
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // $
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // %
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // &
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // (
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // )
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // *
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ,
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // .
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // /
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // 0
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // 1
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // 2
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // 3
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // 4
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // 5
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // 6
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // 7
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // 8
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // 9
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ;
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // <
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // =
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // >
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // ?
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // @
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // A
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // B
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // C
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // D
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // E
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // F
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // G
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // H
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // I
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // J
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // K
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // L
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // M
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // N
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // O
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // P
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // Q
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // R
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // S
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // T
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // U
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // V
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // W
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // X
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // Y
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // Z
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // [
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // \
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ]
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // _
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // a
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // b
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // c
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // d
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // e
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // f
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // g
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // h
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // i
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // j
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // k
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // l
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // m
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // n
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // o
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // p
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // q
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // r
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // s
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // t
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // u
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // v
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // w
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // x
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // y
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // z
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // {
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // |
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // }
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMON: &str = "common lineHeight=8 base=6";
+
+    #[test]
+    fn loads_pages_in_id_order() {
+        let source = format!(
+            "{} pages=2\npage id=1 file=\"b.png\"\npage id=0 file=\"a.png\"\n\
+             char id=65 x=0 y=0 width=1 height=1 xoffset=0 yoffset=0 xadvance=1 page=1\n",
+            COMMON
+        );
+        let mut loaded = Vec::new();
+        let font = BitmapFont::parse_bmfont(&source, |file| {
+            loaded.push(file.to_string());
+            Ok(Image::new(1, 1))
+        })
+        .unwrap();
+
+        assert_eq!(loaded, ["a.png", "b.png"]);
+        assert_eq!(font.pages().len(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_page_ids() {
+        let out_of_range = format!("{} pages=1\npage id=1 file=\"a.png\"\n", COMMON);
+        assert!(BitmapFont::from_bmfont(&out_of_range, vec![Image::new(1, 1)]).is_err());
+
+        // A huge page count fails on the first missing page, without allocating for every page
+        let huge = format!("{} pages=2000000000\npage id=0 file=\"a.png\"\n", COMMON);
+        let error = BitmapFont::from_bmfont(&huge, vec![Image::new(1, 1)]).unwrap_err();
+        assert_eq!(error.to_string(), "Missing `page` line for page 1");
+    }
+}
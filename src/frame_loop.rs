@@ -0,0 +1,330 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::handle_error, App, AppError, ErrorAction, ErrorPhase, RenderContext, TimestepMode,
+};
+
+/// Whether the app should keep running after a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameStatus {
+    Continue,
+    /// The app asked for the rest of the frame to be skipped after an error.
+    SkipFrame,
+    Exit,
+}
+
+/// The update loop, shared by windowed and headless apps.
+/// Elapsed time is turned into calls to `App::update` according to the timestep mode, before
+/// `App::draw` is called.
+#[derive(Debug)]
+pub(crate) struct FrameLoop {
+    timestep_mode: TimestepMode,
+    max_updates_per_frame: Option<u32>,
+    accumulated_time: Duration,
+    update_count: u64,
+}
+
+impl FrameLoop {
+    pub(crate) fn new(timestep_mode: TimestepMode, max_updates_per_frame: Option<u32>) -> Self {
+        Self {
+            timestep_mode,
+            max_updates_per_frame,
+            accumulated_time: Duration::ZERO,
+            update_count: 0,
+        }
+    }
+
+    /// The total number of times `App::update` has been called.
+    pub(crate) fn update_count(&self) -> u64 {
+        self.update_count
+    }
+
+    /// Get how long until the next update is owed.
+    /// Only the fixed timestep waits for a whole target frame time between updates.
+    pub(crate) fn time_until_next_update(&self, target_frame_time: Duration) -> Duration {
+        match self.timestep_mode {
+            TimestepMode::Fixed => target_frame_time.saturating_sub(self.accumulated_time),
+            TimestepMode::Variable | TimestepMode::SemiFixed => Duration::ZERO,
+        }
+    }
+
+    /// Run the updates owed for `elapsed` time, then draw.
+    /// If `elapsed` is `None`, updates are skipped and only `draw` is called.
+    /// Errors are passed to `App::on_error`, and returned if the app should exit.
+    pub(crate) fn run_frame<A: App>(
+        &mut self,
+        app: &mut A,
+        ctx: &mut RenderContext,
+        elapsed: Option<Duration>,
+    ) -> Result<FrameStatus, AppError> {
+        let mut lost_time = Duration::ZERO;
+        let update_count = self.update_count;
+
+        if ctx.paused {
+            // Only a requested step runs while paused, and no time is owed afterwards
+            if std::mem::take(&mut ctx.step_requested) {
+                let status = self.tick(app, ctx, ctx.target_frame_time())?;
+                if status != FrameStatus::Continue {
+                    return Ok(status);
+                }
+            }
+        } else if let Some(elapsed) = elapsed {
+            // The maximum frame time limits real time, so fast-forwarding isn't counted as lag
+            let scale = |time: Duration| {
+                Duration::try_from_secs_f64(time.as_secs_f64() * ctx.time_scale() as f64)
+                    .unwrap_or(Duration::MAX)
+            };
+            lost_time += scale(elapsed.saturating_sub(ctx.max_frame_time()));
+            let elapsed = scale(elapsed.min(ctx.max_frame_time()));
+
+            let target_frame_time = ctx.target_frame_time();
+            let max_updates = self.max_updates_per_frame.unwrap_or(u32::MAX);
+            let mut updates = 0;
+
+            match self.timestep_mode {
+                TimestepMode::Fixed => {
+                    self.accumulated_time += elapsed;
+
+                    while self.accumulated_time >= target_frame_time {
+                        // Drop whole ticks past the limit, so a slow update can't snowball
+                        if updates == max_updates {
+                            let dropped = (self.accumulated_time.as_nanos()
+                                / target_frame_time.as_nanos())
+                                as u32;
+                            self.accumulated_time -= target_frame_time * dropped;
+                            lost_time += target_frame_time * dropped;
+                            break;
+                        }
+
+                        let status = self.tick(app, ctx, target_frame_time)?;
+                        self.accumulated_time -= target_frame_time;
+                        updates += 1;
+
+                        if status != FrameStatus::Continue {
+                            return Ok(status);
+                        }
+                    }
+                }
+
+                TimestepMode::Variable => {
+                    let status = self.tick(app, ctx, elapsed)?;
+                    if status != FrameStatus::Continue {
+                        return Ok(status);
+                    }
+                }
+
+                TimestepMode::SemiFixed => {
+                    let mut remaining = elapsed;
+
+                    while !remaining.is_zero() {
+                        if updates == max_updates {
+                            lost_time += remaining;
+                            break;
+                        }
+
+                        let delta_time = remaining.min(target_frame_time);
+                        let status = self.tick(app, ctx, delta_time)?;
+                        remaining -= delta_time;
+                        updates += 1;
+
+                        if status != FrameStatus::Continue {
+                            return Ok(status);
+                        }
+                    }
+                }
+            }
+        }
+
+        // A stopped simulation runs no ticks, but input still moves on once per frame, so presses
+        // don't stay pressed and text doesn't build up until it resumes. The tick doesn't
+        // advance, so recordings stay in step with the updates
+        if (ctx.paused || ctx.time_scale() == 0.0) && self.update_count == update_count {
+            ctx.input.end_frame();
+        }
+
+        ctx.lost_time = lost_time;
+        ctx.dropped_ticks =
+            (lost_time.as_nanos() / ctx.target_frame_time().as_nanos().max(1)) as u32;
+        ctx.total_dropped_ticks += ctx.dropped_ticks as u64;
+
+        if !lost_time.is_zero() {
+            let result = app.on_lag(lost_time);
+            if handle_error(app, ctx, ErrorPhase::Update, result)? == ErrorAction::SkipFrame {
+                return Ok(FrameStatus::SkipFrame);
+            }
+        }
+
+        // Only the fixed timestep leaves time over between updates to interpolate across
+        ctx.alpha = match self.timestep_mode {
+            TimestepMode::Fixed => (self.accumulated_time.as_secs_f64()
+                / ctx.target_frame_time().as_secs_f64())
+            .clamp(0.0, 1.0) as f32,
+            TimestepMode::Variable | TimestepMode::SemiFixed => 1.0,
+        };
+
+        ctx.stats.end_updates();
+        let draw_start = Instant::now();
+        let result = app.draw(ctx);
+        ctx.stats.end_frame(elapsed, draw_start.elapsed());
+        // The frame is over either way, so skipping is the same as continuing
+        handle_error(app, ctx, ErrorPhase::Draw, result)?;
+
+        if ctx.should_exit() {
+            return Ok(FrameStatus::Exit);
+        }
+
+        Ok(FrameStatus::Continue)
+    }
+
+    /// Run a single update.
+    /// A tick that fails still counts, so the clock and input move on past it.
+    fn tick<A: App>(
+        &mut self,
+        app: &mut A,
+        ctx: &mut RenderContext,
+        delta_time: Duration,
+    ) -> Result<FrameStatus, AppError> {
+        ctx.delta_time = delta_time;
+
+        // Input state advances once per tick, so each update sees every press and release
+        ctx.input.begin_tick(delta_time);
+
+        // Fired timers have already left the queue, so each one is delivered even if an earlier
+        // one asks to skip the frame, and only the update is skipped
+        let mut action = ErrorAction::Continue;
+        for id in ctx.timers.advance(delta_time) {
+            let result = app.on_timer(ctx, id);
+            if handle_error(app, ctx, ErrorPhase::Update, result)? == ErrorAction::SkipFrame {
+                action = ErrorAction::SkipFrame;
+            }
+        }
+
+        if action == ErrorAction::Continue {
+            let update_start = Instant::now();
+            let result = app.update(ctx);
+            ctx.stats.record_update(update_start.elapsed());
+            action = handle_error(app, ctx, ErrorPhase::Update, result)?;
+        }
+
+        ctx.input.update();
+        self.update_count += 1;
+
+        if ctx.should_exit() {
+            return Ok(FrameStatus::Exit);
+        }
+
+        if action == ErrorAction::SkipFrame {
+            return Ok(FrameStatus::SkipFrame);
+        }
+
+        Ok(FrameStatus::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{bail, Result};
+
+    use winit::keyboard::KeyCode;
+
+    use super::*;
+    use crate::{InputEvent, TimerId};
+
+    #[derive(Default)]
+    struct TimerApp {
+        fired: Vec<TimerId>,
+        updates: u32,
+    }
+
+    impl App for TimerApp {
+        fn update(&mut self, _ctx: &mut RenderContext) -> Result<()> {
+            self.updates += 1;
+            Ok(())
+        }
+
+        fn draw(&mut self, _ctx: &mut RenderContext) -> Result<()> {
+            Ok(())
+        }
+
+        fn on_timer(&mut self, _ctx: &mut RenderContext, id: TimerId) -> Result<()> {
+            self.fired.push(id);
+            if id == 1 {
+                bail!("timer failed");
+            }
+            Ok(())
+        }
+
+        fn on_error(&mut self, _ctx: &mut RenderContext, _error: &AppError) -> ErrorAction {
+            ErrorAction::SkipFrame
+        }
+    }
+
+    #[test]
+    fn skipping_a_frame_still_delivers_every_fired_timer() {
+        let frame_time = Duration::from_millis(10);
+        let mut ctx = RenderContext::new_headless(frame_time, frame_time * 4, 1, 1);
+        let mut frame_loop = FrameLoop::new(TimestepMode::Fixed, None);
+        let mut app = TimerApp::default();
+
+        ctx.timers().after(frame_time, 1);
+        ctx.timers().after(frame_time, 2);
+
+        let status = frame_loop
+            .run_frame(&mut app, &mut ctx, Some(frame_time))
+            .unwrap();
+        assert_eq!(status, FrameStatus::SkipFrame);
+        assert_eq!(app.fired, vec![1, 2]);
+        assert_eq!(app.updates, 0);
+        assert!(!ctx.timers().is_scheduled(2));
+
+        // The next frame runs as normal
+        let status = frame_loop
+            .run_frame(&mut app, &mut ctx, Some(frame_time))
+            .unwrap();
+        assert_eq!(status, FrameStatus::Continue);
+        assert_eq!(app.updates, 1);
+    }
+
+    #[test]
+    fn time_scale_applies_after_the_max_frame_time() {
+        let frame_time = Duration::from_millis(16);
+        let mut ctx = RenderContext::new_headless(frame_time, frame_time * 2, 1, 1);
+        let mut frame_loop = FrameLoop::new(TimestepMode::Fixed, None);
+        let mut app = TimerApp::default();
+        ctx.set_time_scale(4.0);
+
+        frame_loop
+            .run_frame(&mut app, &mut ctx, Some(frame_time))
+            .unwrap();
+        assert_eq!(app.updates, 4);
+        assert_eq!(ctx.lost_time(), Duration::ZERO);
+
+        // Only real time past the maximum is lost, scaled to simulation time
+        frame_loop
+            .run_frame(&mut app, &mut ctx, Some(frame_time * 3))
+            .unwrap();
+        assert_eq!(app.updates, 12);
+        assert_eq!(ctx.lost_time(), frame_time * 4);
+    }
+
+    #[test]
+    fn paused_frames_reset_input_without_advancing_the_tick() {
+        let frame_time = Duration::from_millis(16);
+        let mut ctx = RenderContext::new_headless(frame_time, frame_time * 2, 1, 1);
+        let mut frame_loop = FrameLoop::new(TimestepMode::Fixed, None);
+        let mut app = TimerApp::default();
+        ctx.pause();
+
+        ctx.input.inject(InputEvent::KeyDown(KeyCode::Space));
+        ctx.input.inject(InputEvent::Char(' '));
+        frame_loop
+            .run_frame(&mut app, &mut ctx, Some(frame_time))
+            .unwrap();
+
+        assert_eq!(app.updates, 0);
+        assert_eq!(ctx.input.tick(), 0);
+        assert!(!ctx.input.key_pressed(KeyCode::Space));
+        assert!(ctx.input.key_down(KeyCode::Space));
+        assert_eq!(ctx.input.text_input(), "");
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{Canvas, Color};
+
+/// The number of recent frames kept for the rolling FPS and the overlay graph.
+pub const FRAME_HISTORY_LEN: usize = 120;
+
+/// The number of buckets in the frame time histogram.
+/// Each bucket is 1 millisecond wide, and the last one counts every longer frame.
+pub const FRAME_HISTOGRAM_BUCKETS: usize = 50;
+
+/// Timing statistics for recent frames.
+/// Apart from the history and histogram, values are for the last completed frame. During
+/// `App::draw`, the update count and update time are already for the current frame.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    updates: u32,
+    update_time: Duration,
+    draw_time: Duration,
+    present_time: Duration,
+    frame_time: Duration,
+    frame_count: u64,
+    history: VecDeque<Duration>,
+    histogram: [u64; FRAME_HISTOGRAM_BUCKETS],
+
+    // Timings for the frame in progress
+    pending_updates: u32,
+    pending_update_time: Duration,
+    pending_present_time: Duration,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            updates: 0,
+            update_time: Duration::ZERO,
+            draw_time: Duration::ZERO,
+            present_time: Duration::ZERO,
+            frame_time: Duration::ZERO,
+            frame_count: 0,
+            history: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            histogram: [0; FRAME_HISTOGRAM_BUCKETS],
+            pending_updates: 0,
+            pending_update_time: Duration::ZERO,
+            pending_present_time: Duration::ZERO,
+        }
+    }
+}
+
+impl FrameStats {
+    /// Get the number of times `App::update` was called in the last frame.
+    pub fn updates(&self) -> u32 {
+        self.updates
+    }
+
+    /// Get the total time spent in `App::update` in the last frame.
+    pub fn update_time(&self) -> Duration {
+        self.update_time
+    }
+
+    /// Get the time spent in `App::draw` in the last frame, not including presenting.
+    pub fn draw_time(&self) -> Duration {
+        self.draw_time
+    }
+
+    /// Get the time spent in `RenderContext::present` in the last frame.
+    pub fn present_time(&self) -> Duration {
+        self.present_time
+    }
+
+    /// Get the time between the start of the last frame and the one before it.
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// Get the total number of frames drawn.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Get the average frames per second over recent frames.
+    pub fn fps(&self) -> f32 {
+        let total: Duration = self.history.iter().sum();
+        if total.is_zero() {
+            return 0.0;
+        }
+
+        self.history.len() as f32 / total.as_secs_f32()
+    }
+
+    /// Get the frame times of recent frames, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Get the number of frames that took each whole number of milliseconds.
+    /// The last bucket counts every frame that took longer.
+    pub fn histogram(&self) -> &[u64; FRAME_HISTOGRAM_BUCKETS] {
+        &self.histogram
+    }
+
+    /// Clear the histogram, e.g. before measuring a specific scene.
+    pub fn reset_histogram(&mut self) {
+        self.histogram = [0; FRAME_HISTOGRAM_BUCKETS];
+    }
+
+    pub(crate) fn record_update(&mut self, update_time: Duration) {
+        self.pending_updates += 1;
+        self.pending_update_time += update_time;
+    }
+
+    pub(crate) fn record_present(&mut self, present_time: Duration) {
+        self.pending_present_time += present_time;
+    }
+
+    /// Finish the updates for the frame in progress, before it is drawn.
+    pub(crate) fn end_updates(&mut self) {
+        self.updates = std::mem::take(&mut self.pending_updates);
+        self.update_time = std::mem::take(&mut self.pending_update_time);
+    }
+
+    /// Finish the frame in progress.
+    /// Frames that didn't advance time, such as the one after a resize, aren't added to the history.
+    pub(crate) fn end_frame(&mut self, frame_time: Option<Duration>, draw_time: Duration) {
+        self.present_time = std::mem::take(&mut self.pending_present_time);
+        self.draw_time = draw_time.saturating_sub(self.present_time);
+        self.frame_count += 1;
+
+        if let Some(frame_time) = frame_time {
+            self.frame_time = frame_time;
+
+            if self.history.len() == FRAME_HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(frame_time);
+
+            let bucket = (frame_time.as_millis() as usize).min(FRAME_HISTOGRAM_BUCKETS - 1);
+            self.histogram[bucket] += 1;
+        }
+    }
+
+    /// Draw a graph of recent frame times, and the FPS, in the top left of the frame.
+    /// Bars are green when a frame met the target frame time, yellow when it took up to twice as
+    /// long, and red otherwise.
+    pub(crate) fn draw_overlay(&self, canvas: &mut Canvas, target_frame_time: Duration) {
+        const GRAPH_HEIGHT: i32 = 40;
+        const PADDING: i32 = 2;
+
+        let width = (FRAME_HISTORY_LEN as i32 + PADDING * 2).min(canvas.width() as i32);
+        let height = (GRAPH_HEIGHT + 8 + PADDING * 3).min(canvas.height() as i32);
+        let background = Color::rgba(0, 0, 0, 0xa0);
+        for y in 0..height {
+            for x in 0..width {
+                canvas.blend_pixel(x, y, background);
+            }
+        }
+
+        let text = format!(
+            "{:.0} FPS {:.1}ms",
+            self.fps(),
+            self.frame_time.as_secs_f32() * 1000.0
+        );
+        canvas.draw_text(PADDING, PADDING, &text, Color::WHITE);
+
+        // The graph is scaled so twice the target frame time fills it
+        let target_ms = target_frame_time.as_secs_f32() * 1000.0;
+        let scale = GRAPH_HEIGHT as f32 / (target_ms * 2.0).max(1.0);
+        let bottom = height - PADDING;
+
+        for (i, frame_time) in self.history().enumerate() {
+            let ms = frame_time.as_secs_f32() * 1000.0;
+            let bar = ((ms * scale).ceil() as i32).clamp(1, GRAPH_HEIGHT);
+            let color = if ms <= target_ms {
+                Color::GREEN
+            } else if ms <= target_ms * 2.0 {
+                Color::YELLOW
+            } else {
+                Color::RED
+            };
+            canvas.fill_rect(PADDING + i as i32, bottom - bar, 1, bar, color);
+        }
+
+        let target_y = bottom - (target_ms * scale).round() as i32;
+        canvas.draw_line(
+            PADDING,
+            target_y,
+            PADDING + FRAME_HISTORY_LEN as i32 - 1,
+            target_y,
+            Color::GRAY,
+        );
+    }
+}
@@ -0,0 +1,80 @@
+/// Where the frame buffer is drawn in the window.
+/// The frame is scaled up by the largest whole number that fits, and centred, with a letterbox
+/// around it. This matches how `pixels` presents the frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FrameTransform {
+    frame_width: u32,
+    frame_height: u32,
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl FrameTransform {
+    /// A frame drawn at its own size, with no letterbox, e.g. when running headless.
+    pub(crate) fn identity(frame_width: u32, frame_height: u32) -> Self {
+        Self {
+            frame_width,
+            frame_height,
+            scale: 1.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+        }
+    }
+
+    /// A frame drawn in a window of the given size, in physical pixels.
+    pub(crate) fn new(
+        frame_width: u32,
+        frame_height: u32,
+        window_width: u32,
+        window_height: u32,
+    ) -> Self {
+        let (frame_w, frame_h) = (frame_width as f64, frame_height as f64);
+        let (window_w, window_h) = (window_width as f64, window_height as f64);
+
+        // The frame is never scaled down, so a small window crops it instead
+        let width_ratio = (window_w / frame_w).max(1.0);
+        let height_ratio = (window_h / frame_h).max(1.0);
+        let scale = width_ratio.min(height_ratio).floor();
+
+        Self {
+            frame_width,
+            frame_height,
+            scale,
+            offset_x: ((window_w - frame_w * scale) / 2.0).floor(),
+            offset_y: ((window_h - frame_h * scale) / 2.0).floor(),
+        }
+    }
+
+    /// Get the number of window pixels per frame pixel.
+    pub(crate) fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Convert a window position to a frame position, which may be outside the frame.
+    pub(crate) fn window_to_frame(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (x - self.offset_x) / self.scale,
+            (y - self.offset_y) / self.scale,
+        )
+    }
+
+    /// Convert a frame position to a window position.
+    pub(crate) fn frame_to_window(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            x * self.scale + self.offset_x,
+            y * self.scale + self.offset_y,
+        )
+    }
+
+    /// Get the frame pixel under a window position.
+    /// Returns `None` if the position is in the letterbox.
+    pub(crate) fn pixel_at(&self, x: f64, y: f64) -> Option<(i32, i32)> {
+        let (x, y) = self.window_to_frame(x, y);
+        let (x, y) = (x.floor(), y.floor());
+
+        let inside =
+            x >= 0.0 && y >= 0.0 && x < self.frame_width as f64 && y < self.frame_height as f64;
+        inside.then_some((x as i32, y as i32))
+    }
+}
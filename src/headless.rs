@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{
+    error::handle_error,
+    frame_loop::{FrameLoop, FrameStatus},
+    recording::{finish_session, start_session},
+    App, AppError, ErrorPhase, Image, RenderContext,
+};
+
+/// A predicate checked after each headless frame. The run stops when it returns true.
+pub type HeadlessPredicate<A> = Box<dyn FnMut(&A, &RenderContext) -> bool>;
+
+/// Configures a headless run of an app, started with `App::run_headless`.
+/// The run stops after the maximum number of frames or updates, when the predicate returns true,
+/// or when the app exits, whichever comes first.
+pub struct HeadlessConfig<A> {
+    max_frames: Option<u64>,
+    max_updates: Option<u64>,
+    frame_time: Option<Duration>,
+    until: Option<HeadlessPredicate<A>>,
+}
+
+impl<A> HeadlessConfig<A> {
+    /// Run until the app exits.
+    pub fn new() -> Self {
+        Self {
+            max_frames: None,
+            max_updates: None,
+            frame_time: None,
+            until: None,
+        }
+    }
+
+    /// Run for the given number of frames.
+    pub fn frames(frames: u64) -> Self {
+        Self::new().with_max_frames(frames)
+    }
+
+    /// Run until `App::update` has been called the given number of times.
+    pub fn updates(updates: u64) -> Self {
+        Self::new().with_max_updates(updates)
+    }
+
+    /// Run until the predicate returns true.
+    pub fn until(predicate: impl FnMut(&A, &RenderContext) -> bool + 'static) -> Self {
+        Self::new().with_predicate(predicate)
+    }
+
+    pub fn with_max_frames(mut self, frames: u64) -> Self {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    /// Stop once `App::update` has been called at least this many times, at the end of a frame.
+    /// A paused app runs no updates, so set a maximum number of frames too if the app may pause.
+    pub fn with_max_updates(mut self, updates: u64) -> Self {
+        self.max_updates = Some(updates);
+        self
+    }
+
+    pub fn with_predicate(
+        mut self,
+        predicate: impl FnMut(&A, &RenderContext) -> bool + 'static,
+    ) -> Self {
+        self.until = Some(Box::new(predicate));
+        self
+    }
+
+    /// Set how much simulated time passes each frame.
+    /// If not set, the target frame time of the app is used, so each frame runs one update.
+    pub fn with_frame_time(mut self, frame_time: Duration) -> Self {
+        self.frame_time = Some(frame_time);
+        self
+    }
+}
+
+impl<A> Default for HeadlessConfig<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The result of a headless run.
+#[derive(Debug)]
+pub struct HeadlessRun<A> {
+    /// The app, in its state after the last frame.
+    pub app: A,
+    /// The frame buffer after the last frame.
+    pub frame: Image,
+    /// The number of frames that were drawn.
+    pub frames: u64,
+    /// The number of times `App::update` was called.
+    pub updates: u64,
+    /// The total simulated time.
+    pub elapsed: Duration,
+}
+
+pub(crate) fn run_headless<A: App>(
+    mut app: A,
+    mut config: HeadlessConfig<A>,
+) -> Result<HeadlessRun<A>> {
+    let settings = app.init()?;
+
+    let mut render_context = RenderContext::new_headless(
+        settings.target_frame_time,
+        settings.max_frame_time,
+        settings.frame_width,
+        settings.frame_height,
+    );
+
+    render_context.set_stats_overlay(settings.stats_overlay);
+    start_session(&settings, &mut render_context.input)?;
+
+    let frame_time = config.frame_time.unwrap_or(settings.target_frame_time);
+    let mut frame_loop = FrameLoop::new(settings.timestep_mode, settings.max_updates_per_frame);
+    let mut frames = 0;
+    let mut elapsed = Duration::ZERO;
+
+    let mut run_frames = || -> Result<(), AppError> {
+        while config.max_frames.map_or(true, |max| frames < max)
+            && config
+                .max_updates
+                .map_or(true, |max| frame_loop.update_count() < max)
+        {
+            let status = frame_loop.run_frame(&mut app, &mut render_context, Some(frame_time))?;
+            frames += 1;
+            elapsed += frame_time;
+
+            if status == FrameStatus::Exit {
+                break;
+            }
+
+            if let Some(until) = config.until.as_mut() {
+                if until(&app, &render_context) {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    };
+
+    // Let the app clean up and save any input recording even if it failed, so the failure can be
+    // replayed
+    let result = run_frames();
+    let exit_result = app.on_exit(&mut render_context);
+    let exit_result = handle_error(&mut app, &mut render_context, ErrorPhase::Exit, exit_result);
+    finish_session(&settings, &mut render_context.input)?;
+    result?;
+    exit_result?;
+
+    let frame = Image::from_rgba(
+        render_context.frame_width(),
+        render_context.frame_height(),
+        render_context.frame().to_vec(),
+    )?;
+
+    Ok(HeadlessRun {
+        app,
+        frame,
+        frames,
+        updates: frame_loop.update_count(),
+        elapsed,
+    })
+}
@@ -0,0 +1,191 @@
+use std::path::Path;
+
+#[cfg(feature = "png")]
+use anyhow::Context;
+use anyhow::{ensure, Result};
+
+use crate::{codec, Canvas, Color, ImageError, ImageErrorKind, Rect};
+
+/// An owned RGBA image, with 8 bits per channel and no padding between rows.
+/// Images can be drawn to with `canvas`, and drawn to the frame with `Canvas::blit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Create a fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::filled(width, height, Color::TRANSPARENT)
+    }
+
+    /// Create an image filled with a single color.
+    pub fn filled(width: u32, height: u32, color: Color) -> Self {
+        Self {
+            width,
+            height,
+            data: color.to_array().repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Create an image from raw RGBA data.
+    /// Fails if the length of `data` is not `width * height * 4`.
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> Result<Self> {
+        ensure!(
+            data.len() == width as usize * height as usize * 4,
+            "Expected {} bytes of RGBA data for a {}x{} image, got {}",
+            width as usize * height as usize * 4,
+            width,
+            height,
+            data.len()
+        );
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Load an image from a file.
+    /// The format is detected from the contents of the file, and must have its cargo feature
+    /// enabled.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|error| ImageError::from(ImageErrorKind::Io(error)).with_path(path))?;
+
+        Self::from_bytes(&bytes).map_err(|error| error.with_path(path))
+    }
+
+    /// Decode an image from the contents of an image file.
+    /// The format is detected from the data, and must have its cargo feature enabled.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        Ok(codec::decode(bytes)?)
+    }
+
+    /// Encode the image as an 8-bit RGBA PNG file.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> Vec<u8> {
+        codec::encode_png(self)
+    }
+
+    /// Save the image to a PNG file.
+    #[cfg(feature = "png")]
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_png())
+            .with_context(|| format!("Failed to write image {}", path.display()))
+    }
+
+    /// Get the width of the image, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the bounds of the image, positioned at the origin
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Get the raw RGBA data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the raw RGBA data (mutable)
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Consume the image, returning the raw RGBA data
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Get the color of a single pixel.
+    /// Returns `None` if the pixel is outside of the image.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<Color> {
+        if !self.bounds().contains(x, y) {
+            return None;
+        }
+
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        Some(Color::from_array([
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+            self.data[i + 3],
+        ]))
+    }
+
+    /// Get a canvas for drawing to the image
+    pub fn canvas(&mut self) -> Canvas<'_> {
+        Canvas::new(&mut self.data, self.width, self.height)
+    }
+
+    /// Copy a region of the image into a new image.
+    /// The region is clipped to the bounds of the image.
+    pub fn sub_image(&self, rect: Rect) -> Image {
+        let Some(rect) = rect.intersection(&self.bounds()) else {
+            return Image::new(0, 0);
+        };
+
+        let mut data = Vec::with_capacity(rect.width as usize * rect.height as usize * 4);
+        for y in rect.y..rect.bottom() {
+            let start = (y as usize * self.width as usize + rect.x as usize) * 4;
+            data.extend_from_slice(&self.data[start..start + rect.width as usize * 4]);
+        }
+
+        Image {
+            width: rect.width,
+            height: rect.height,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_rgba_checks_the_length() {
+        assert!(Image::from_rgba(2, 2, vec![0; 16]).is_ok());
+        assert!(Image::from_rgba(2, 2, vec![0; 15]).is_err());
+    }
+
+    #[test]
+    fn get_pixel_is_none_outside_the_image() {
+        let image = Image::filled(2, 2, Color::RED);
+
+        assert_eq!(image.get_pixel(1, 1), Some(Color::RED));
+        assert_eq!(image.get_pixel(-1, 0), None);
+        assert_eq!(image.get_pixel(0, 2), None);
+        assert_eq!(image.get_pixel(i32::MIN, i32::MAX), None);
+    }
+
+    #[test]
+    fn sub_image_is_clipped_to_the_image() {
+        let mut image = Image::new(4, 4);
+        image.canvas().set_pixel(0, 0, Color::RED);
+        image.canvas().set_pixel(3, 3, Color::BLUE);
+
+        let corner = image.sub_image(Rect::new(-2, -2, 3, 3));
+        assert_eq!((corner.width(), corner.height()), (1, 1));
+        assert_eq!(corner.get_pixel(0, 0), Some(Color::RED));
+
+        let corner = image.sub_image(Rect::new(3, 3, u32::MAX, u32::MAX));
+        assert_eq!((corner.width(), corner.height()), (1, 1));
+        assert_eq!(corner.get_pixel(0, 0), Some(Color::BLUE));
+
+        assert_eq!(image.sub_image(Rect::new(4, 0, 1, 1)).width(), 0);
+    }
+}
@@ -1,12 +1,54 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, Event, Modifiers, MouseButton, WindowEvent},
+    event::{ElementState, Event, Ime, Modifiers, MouseButton, MouseScrollDelta, WindowEvent},
     keyboard::{KeyCode, ModifiersKeyState, PhysicalKey},
     window::WindowId,
 };
 
+use crate::{
+    action_map::{trigger_down, trigger_pressed, trigger_released},
+    frame_transform::FrameTransform,
+    ActionMap, InputRecording,
+};
+
+/// The number of logical pixels of touchpad scrolling that count as one line of mouse wheel
+/// scrolling.
+pub const SCROLL_PIXELS_PER_LINE: f32 = 20.0;
+
+/// Settings for software key repeat, which works the same on every platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyRepeat {
+    /// How long a key is held before it starts repeating.
+    pub delay: Duration,
+    /// The time between repeats, once a key is repeating.
+    pub interval: Duration,
+}
+
+impl KeyRepeat {
+    pub fn new(delay: Duration, interval: Duration) -> Self {
+        Self { delay, interval }
+    }
+}
+
+impl Default for KeyRepeat {
+    /// Repeat after half a second, 30 times a second.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(1) / 30)
+    }
+}
+
+/// How long a key has been held, for software key repeat.
+#[derive(Debug, Clone, Copy)]
+struct KeyHold {
+    held: Duration,
+    next_repeat: Duration,
+}
+
 /// The state of keyboard modifiers.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub struct KeyboardModifiers {
@@ -44,11 +86,11 @@ impl KeyboardModifiers {
 /// The state of a key or button.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum InputState {
-    /// The input was pressed this frame.
+    /// The input was pressed this tick.
     Pressed,
     /// The input is currently held down.
     Down,
-    /// The input was released this frame.
+    /// The input was released this tick.
     Released,
 }
 
@@ -61,7 +103,33 @@ impl From<ElementState> for InputState {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+/// A synthetic input event, which can be injected into an `InputManager`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    /// A key was pressed.
+    KeyDown(KeyCode),
+    /// A key was released.
+    KeyUp(KeyCode),
+    /// A held key was repeated by the OS.
+    KeyRepeat(KeyCode),
+    /// The keyboard modifiers changed.
+    ModifiersChanged(KeyboardModifiers),
+    /// A character was typed, or committed by an input method.
+    Char(char),
+    /// The cursor moved, in physical pixels relative to the top left of the window.
+    MouseMove { x: f64, y: f64 },
+    /// The cursor left the window.
+    MouseLeave,
+    /// A mouse button was pressed.
+    MouseDown(MouseButton),
+    /// A mouse button was released.
+    MouseUp(MouseButton),
+    /// The mouse wheel was scrolled, in lines.
+    /// Positive values move the content right and down.
+    MouseWheel { x: f32, y: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum InputType {
     Key(KeyCode),
     Mouse(MouseButton),
@@ -72,87 +140,443 @@ enum InputType {
 /// Make sure to call `handle_keyboard_event` with keyboard events from winit's event loop.
 #[derive(Debug)]
 pub struct InputManager {
-    window_id: WindowId,
+    window_id: Option<WindowId>,
     input_map: HashMap<InputType, InputState>,
+    repeated_keys: HashSet<KeyCode>,
+    key_repeat: Option<KeyRepeat>,
+    key_holds: HashMap<KeyCode, KeyHold>,
+    key_text: HashMap<KeyCode, String>,
     key_modifiers: KeyboardModifiers,
-    cursor_position: PhysicalPosition<f64>,
+    cursor_position: Option<PhysicalPosition<f64>>,
+    tick_cursor_position: Option<PhysicalPosition<f64>>,
+    frame_transform: Option<FrameTransform>,
+    scale_factor: f64,
+    scroll_delta: (f32, f32),
+    text_input: String,
+    ime_preedit: Option<(String, Option<(usize, usize)>)>,
+    actions: ActionMap,
+    tick: u64,
+    recording: Option<(u64, InputRecording)>,
+    replay: Option<Replay>,
+}
+
+/// A recording being replayed, and how far through it is.
+#[derive(Debug)]
+struct Replay {
+    recording: InputRecording,
+    start_tick: u64,
+    next_event: usize,
+    /// The app's software key repeat setting, restored when the replay finishes.
+    key_repeat: Option<KeyRepeat>,
 }
 
 impl InputManager {
+    /// Creates an input manager that isn't attached to a window.
+    /// Input can only be injected, which is useful for testing code that reads input.
+    pub fn detached() -> Self {
+        Self::new(None)
+    }
+
     /// Creates a new input manager.
-    pub(crate) fn new(window_id: WindowId) -> Self {
+    /// Without a window ID, no window events will be handled.
+    pub(crate) fn new(window_id: Option<WindowId>) -> Self {
         Self {
             window_id,
             input_map: HashMap::new(),
+            repeated_keys: HashSet::new(),
+            key_repeat: None,
+            key_holds: HashMap::new(),
+            key_text: HashMap::new(),
             key_modifiers: KeyboardModifiers::default(),
-            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            cursor_position: None,
+            tick_cursor_position: None,
+            frame_transform: None,
+            scale_factor: 1.0,
+            scroll_delta: (0.0, 0.0),
+            text_input: String::new(),
+            ime_preedit: None,
+            actions: ActionMap::new(),
+            tick: 0,
+            recording: None,
+            replay: None,
         }
     }
 
+    /// Set where the frame buffer is drawn in the window, for converting the cursor position.
+    pub(crate) fn set_frame_transform(&mut self, frame_transform: FrameTransform) {
+        self.frame_transform = Some(frame_transform);
+    }
+
+    /// Get where the frame buffer is drawn in the window.
+    pub(crate) fn frame_transform(&self) -> Option<FrameTransform> {
+        self.frame_transform
+    }
+
+    /// Set the window's scale factor, for converting touchpad scrolling to lines.
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
     /// Updates the input manager with events from winit's event loop.
     pub(crate) fn handle_event(&mut self, event: &winit::event::Event<()>) {
+        if let Event::WindowEvent {
+            event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+            ..
+        } = event
+        {
+            self.scale_factor = *scale_factor;
+        }
+
+        // Real input would make a replay diverge from its recording
+        if self.replay.is_some() {
+            return;
+        }
+
         match event {
-            Event::WindowEvent { window_id, event } if *window_id == self.window_id => {
-                match event {
+            Event::WindowEvent { window_id, event } if Some(*window_id) == self.window_id => {
+                let input_event = match event {
                     WindowEvent::KeyboardInput {
                         device_id: _,
                         event,
                         is_synthetic: false,
-                    } if !event.repeat => {
-                        if let PhysicalKey::Code(key_code) = event.physical_key {
-                            let input = InputType::Key(key_code);
+                    } => {
+                        // Text repeats while a key is held, but the key is only pressed once.
+                        // Software repeat types the key's text again itself
+                        let text = event.text.as_deref().unwrap_or_default();
+                        if event.state == ElementState::Pressed
+                            && !(event.repeat && self.key_repeat.is_some())
+                        {
+                            self.inject_text(text);
+                        }
 
+                        if let PhysicalKey::Code(key_code) = event.physical_key {
                             match event.state {
-                                ElementState::Pressed => match self.input_map.get(&input) {
-                                    Some(&InputState::Released) | None => {
-                                        self.input_map.insert(input, InputState::Pressed);
-                                    }
-                                    Some(&InputState::Pressed) | Some(&InputState::Down) => {
-                                        self.input_map.insert(input, InputState::Down);
-                                    }
-                                },
-
+                                ElementState::Pressed if !event.repeat => {
+                                    self.key_text.insert(key_code, text.to_string());
+                                }
                                 ElementState::Released => {
-                                    self.input_map.insert(input, InputState::Released);
+                                    self.key_text.remove(&key_code);
                                 }
+                                ElementState::Pressed => {}
                             }
                         }
+
+                        match (event.physical_key, event.state) {
+                            // Software repeat replaces the OS's, so it works the same everywhere
+                            (PhysicalKey::Code(key_code), ElementState::Pressed)
+                                if event.repeat && self.key_repeat.is_none() =>
+                            {
+                                InputEvent::KeyRepeat(key_code)
+                            }
+                            _ if event.repeat => return,
+                            (PhysicalKey::Code(key_code), ElementState::Pressed) => {
+                                InputEvent::KeyDown(key_code)
+                            }
+                            (PhysicalKey::Code(key_code), ElementState::Released) => {
+                                InputEvent::KeyUp(key_code)
+                            }
+                            _ => return,
+                        }
+                    }
+
+                    WindowEvent::Ime(ime) => {
+                        match ime {
+                            Ime::Preedit(text, cursor) if !text.is_empty() => {
+                                self.ime_preedit = Some((text.clone(), *cursor));
+                            }
+                            Ime::Preedit(..) | Ime::Enabled | Ime::Disabled => {
+                                self.ime_preedit = None;
+                            }
+                            Ime::Commit(text) => self.inject_text(text),
+                        }
+                        return;
                     }
 
                     WindowEvent::ModifiersChanged(mods) => {
-                        self.key_modifiers.update(mods);
+                        let mut modifiers = KeyboardModifiers::default();
+                        modifiers.update(mods);
+                        InputEvent::ModifiersChanged(modifiers)
                     }
 
-                    WindowEvent::CursorMoved { position, .. } => {
-                        self.cursor_position = *position;
+                    WindowEvent::CursorMoved { position, .. } => InputEvent::MouseMove {
+                        x: position.x,
+                        y: position.y,
+                    },
+
+                    WindowEvent::CursorLeft { .. } => InputEvent::MouseLeave,
+
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let (x, y) = match *delta {
+                            MouseScrollDelta::LineDelta(x, y) => (x, y),
+                            // Touchpads scroll in physical pixels, so they're converted to lines
+                            MouseScrollDelta::PixelDelta(position) => {
+                                let pixels_per_line =
+                                    SCROLL_PIXELS_PER_LINE as f64 * self.scale_factor;
+                                (
+                                    (position.x / pixels_per_line) as f32,
+                                    (position.y / pixels_per_line) as f32,
+                                )
+                            }
+                        };
+                        InputEvent::MouseWheel { x, y }
                     }
 
-                    WindowEvent::MouseInput { state, button, .. } => {
-                        let input = InputType::Mouse(*button);
+                    WindowEvent::MouseInput { state, button, .. } => match state {
+                        ElementState::Pressed => InputEvent::MouseDown(*button),
+                        ElementState::Released => InputEvent::MouseUp(*button),
+                    },
 
-                        match state {
-                            ElementState::Pressed => match self.input_map.get(&input) {
-                                Some(&InputState::Released) | None => {
-                                    self.input_map.insert(input, InputState::Pressed);
-                                }
-                                Some(&InputState::Pressed) | Some(&InputState::Down) => {
-                                    self.input_map.insert(input, InputState::Down);
-                                }
-                            },
-                            ElementState::Released => {
-                                self.input_map.insert(input, InputState::Released);
+                    _ => return,
+                };
+
+                self.inject(input_event);
+            }
+            _ => {}
+        };
+    }
+
+    /// Inject typed text, one character at a time.
+    /// Control characters, such as those typed by Enter or Backspace, are left out.
+    fn inject_text(&mut self, text: &str) {
+        for character in text.chars().filter(|character| !character.is_control()) {
+            self.inject(InputEvent::Char(character));
+        }
+    }
+
+    /// Inject a synthetic input event, as if it came from the window.
+    /// Injected input goes through the same pressed, down and released states as real input, and
+    /// works without a window, so it can be used to script input in tests.
+    pub fn inject(&mut self, event: InputEvent) {
+        if let Some((start_tick, recording)) = self.recording.as_mut() {
+            recording.push(self.tick - *start_tick, event);
+        }
+
+        match event {
+            InputEvent::KeyDown(key_code) => self.press(InputType::Key(key_code)),
+            InputEvent::KeyUp(key_code) => self.release(InputType::Key(key_code)),
+            InputEvent::KeyRepeat(key_code) => {
+                self.repeated_keys.insert(key_code);
+            }
+            InputEvent::ModifiersChanged(modifiers) => self.key_modifiers = modifiers,
+            InputEvent::Char(character) => self.text_input.push(character),
+            InputEvent::MouseMove { x, y } => {
+                self.cursor_position = Some(PhysicalPosition::new(x, y))
+            }
+            InputEvent::MouseLeave => self.cursor_position = None,
+            InputEvent::MouseDown(button) => self.press(InputType::Mouse(button)),
+            InputEvent::MouseUp(button) => self.release(InputType::Mouse(button)),
+            InputEvent::MouseWheel { x, y } => {
+                self.scroll_delta.0 += x;
+                self.scroll_delta.1 += y;
+            }
+        }
+    }
+
+    fn press(&mut self, input: InputType) {
+        match self.input_map.get(&input) {
+            Some(&InputState::Released) | None => {
+                self.input_map.insert(input, InputState::Pressed);
+            }
+            Some(&InputState::Pressed) | Some(&InputState::Down) => {
+                self.input_map.insert(input, InputState::Down);
+            }
+        }
+    }
+
+    fn release(&mut self, input: InputType) {
+        self.input_map.insert(input, InputState::Released);
+    }
+
+    /// Start recording every input event, replacing any recording in progress.
+    /// Inputs that are already held are recorded as the first events, so the recording starts
+    /// from the same state when replayed.
+    pub fn start_recording(&mut self) {
+        let mut recording = InputRecording::new();
+        recording.set_key_repeat(self.key_repeat);
+
+        if let Some(position) = self.cursor_position {
+            recording.push(
+                0,
+                InputEvent::MouseMove {
+                    x: position.x,
+                    y: position.y,
+                },
+            );
+        }
+        if self.key_modifiers != KeyboardModifiers::default() {
+            recording.push(0, InputEvent::ModifiersChanged(self.key_modifiers));
+        }
+        for (input, state) in &self.input_map {
+            if *state != InputState::Released {
+                let event = match *input {
+                    InputType::Key(key_code) => InputEvent::KeyDown(key_code),
+                    InputType::Mouse(button) => InputEvent::MouseDown(button),
+                };
+                recording.push(0, event);
+            }
+        }
+        recording.set_ticks(0);
+
+        self.recording = Some((self.tick, recording));
+    }
+
+    /// Stop recording, and return the recording.
+    /// Returns `None` if no recording was in progress.
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        let (start_tick, mut recording) = self.recording.take()?;
+        recording.set_ticks(self.tick - start_tick);
+        Some(recording)
+    }
+
+    /// Returns true if input is being recorded
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Replay a recording, starting from the next tick.
+    /// All input state is reset, and input from the window is ignored until the replay finishes.
+    /// The recording's software key repeat setting is used until then too.
+    pub fn start_replay(&mut self, recording: InputRecording) {
+        self.stop_replay();
+        let key_repeat = std::mem::replace(&mut self.key_repeat, recording.key_repeat());
+
+        self.input_map.clear();
+        self.repeated_keys.clear();
+        self.key_holds.clear();
+        self.key_modifiers = KeyboardModifiers::default();
+        self.cursor_position = None;
+        self.tick_cursor_position = None;
+        self.scroll_delta = (0.0, 0.0);
+        self.text_input.clear();
+        self.ime_preedit = None;
+
+        self.replay = Some(Replay {
+            recording,
+            start_tick: self.tick,
+            next_event: 0,
+            key_repeat,
+        });
+    }
+
+    /// Stop replaying, and accept input from the window again.
+    pub fn stop_replay(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            self.key_repeat = replay.key_repeat;
+            self.key_holds.clear();
+        }
+    }
+
+    /// Returns true if a recording is being replayed
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Get the number of fixed update ticks that have run.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Apply the replayed events and software key repeats for the next tick, which advances by
+    /// `delta_time`. Called before each `App::update`.
+    pub(crate) fn begin_tick(&mut self, delta_time: Duration) {
+        self.apply_replay();
+        self.apply_key_repeat(delta_time);
+    }
+
+    fn apply_replay(&mut self) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+
+        let tick = self.tick - replay.start_tick;
+        let mut events = Vec::new();
+        while let Some(recorded) = replay.recording.events().get(replay.next_event) {
+            if recorded.tick > tick {
+                break;
+            }
+            events.push(recorded.event);
+            replay.next_event += 1;
+        }
+
+        for event in events {
+            self.inject(event);
+        }
+    }
+
+    fn apply_key_repeat(&mut self, delta_time: Duration) {
+        let Some(key_repeat) = self.key_repeat else {
+            return;
+        };
+
+        let input_map = &self.input_map;
+        let mut repeated_text = String::new();
+        self.key_holds.retain(|key_code, _| {
+            matches!(
+                input_map.get(&InputType::Key(*key_code)),
+                Some(&InputState::Pressed) | Some(&InputState::Down)
+            )
+        });
+
+        for (input, state) in &self.input_map {
+            let InputType::Key(key_code) = *input else {
+                continue;
+            };
+
+            match state {
+                InputState::Pressed => {
+                    self.key_holds.insert(
+                        key_code,
+                        KeyHold {
+                            held: Duration::ZERO,
+                            next_repeat: key_repeat.delay,
+                        },
+                    );
+                }
+                InputState::Down => {
+                    let hold = self.key_holds.entry(key_code).or_insert(KeyHold {
+                        held: Duration::ZERO,
+                        next_repeat: key_repeat.delay,
+                    });
+                    hold.held += delta_time;
+
+                    // At most one repeat per tick, so a slow tick doesn't cause a burst of them
+                    if hold.held >= hold.next_repeat {
+                        self.repeated_keys.insert(key_code);
+                        hold.next_repeat = (hold.next_repeat + key_repeat.interval).max(hold.held);
+
+                        // A replay already has the repeated text in its recording
+                        if self.replay.is_none() {
+                            if let Some(text) = self.key_text.get(&key_code) {
+                                repeated_text.push_str(text);
                             }
                         }
                     }
-                    _ => {}
                 }
+                InputState::Released => {}
             }
-            _ => {}
-        };
+        }
+
+        self.inject_text(&repeated_text);
     }
 
-    /// Updates the state of input keys. Must be called once per render frame.
-    pub(crate) fn update(&mut self) {
+    /// Updates the state of input keys. Must be called once after each fixed update tick.
+    /// This is called by the event loop, so should only be called when driving an input manager
+    /// by hand, such as in tests.
+    pub fn update(&mut self) {
+        self.end_frame();
+        self.tick += 1;
+
+        if let Some(replay) = self.replay.as_ref() {
+            if self.tick - replay.start_tick >= replay.recording.ticks() {
+                self.stop_replay();
+            }
+        }
+    }
+
+    /// Move pressed inputs to down and forget released ones, and clear the text and scrolling,
+    /// without advancing the tick. Used for frames where the simulation is paused, so input
+    /// doesn't build up until it resumes.
+    pub(crate) fn end_frame(&mut self) {
         self.input_map.retain(|_, state| match state {
             InputState::Pressed => {
                 *state = InputState::Down;
@@ -161,15 +585,85 @@ impl InputManager {
             InputState::Released => false,
             InputState::Down => true,
         });
+        self.repeated_keys.clear();
+        self.scroll_delta = (0.0, 0.0);
+        self.text_input.clear();
+        self.tick_cursor_position = self.cursor_position;
+    }
+
+    /// Get the state of the keyboard modifiers.
+    pub fn modifiers(&self) -> KeyboardModifiers {
+        self.key_modifiers
+    }
+
+    /// Get the frame buffer pixel under the cursor.
+    /// Returns `None` if the cursor is outside the window, or over the letterbox around the frame.
+    /// Without a window, such as when testing with `InputManager::detached`, window positions are
+    /// used as they are.
+    pub fn mouse_position(&self) -> Option<(i32, i32)> {
+        let position = self.cursor_position?;
+        match self.frame_transform {
+            Some(transform) => transform.pixel_at(position.x, position.y),
+            None => Some((position.x.floor() as i32, position.y.floor() as i32)),
+        }
     }
 
-    /// Returns true if the key was pressed this frame.
+    /// Get the cursor position in physical pixels, relative to the top left of the window.
+    /// Returns `None` if the cursor is outside the window.
+    pub fn mouse_window_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+            .map(|position| (position.x, position.y))
+    }
+
+    /// Get how far the cursor moved this tick, in frame buffer pixels.
+    /// Movement is fractional when the frame is scaled up, and is zero on the tick the cursor
+    /// enters or leaves the window.
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        let (Some(current), Some(previous)) = (self.cursor_position, self.tick_cursor_position)
+        else {
+            return (0.0, 0.0);
+        };
+
+        let scale = self
+            .frame_transform
+            .map_or(1.0, |transform| transform.scale());
+        (
+            ((current.x - previous.x) / scale) as f32,
+            ((current.y - previous.y) / scale) as f32,
+        )
+    }
+
+    /// Get the distance scrolled with the mouse wheel or touchpad this tick, in lines.
+    /// Positive values move the content right and down, as when scrolling up to reveal more
+    /// content above. Touchpad scrolling is converted to lines using `SCROLL_PIXELS_PER_LINE`.
+    pub fn scroll_delta(&self) -> (f32, f32) {
+        self.scroll_delta
+    }
+
+    /// Get the text typed this tick, including text committed by an input method.
+    /// Follows the keyboard layout, dead keys and key repeat, so it is what a text field should
+    /// insert. Control characters are left out, so use key presses for Enter, Backspace and Tab.
+    pub fn text_input(&self) -> &str {
+        &self.text_input
+    }
+
+    /// Get the text an input method is composing, which hasn't been committed yet.
+    /// Also returns the byte range of the cursor within the text, if it should be shown. Text
+    /// fields usually draw it underlined at the cursor. Only available when IME is enabled with
+    /// `RenderContext::enable_ime`.
+    pub fn ime_preedit(&self) -> Option<(&str, Option<(usize, usize)>)> {
+        self.ime_preedit
+            .as_ref()
+            .map(|(text, cursor)| (text.as_str(), *cursor))
+    }
+
+    /// Returns true if the key was pressed this tick.
     pub fn key_pressed(&self, key_code: KeyCode) -> bool {
         self.input_map.get(&InputType::Key(key_code)) == Some(&InputState::Pressed)
     }
 
     /// Returns true if the key is currently down.
-    /// Will return true for multiple frames if the key is held down, including the frame it was
+    /// Will return true for multiple ticks if the key is held down, including the tick it was
     /// pressed.
     pub fn key_down(&self, key_code: KeyCode) -> bool {
         matches!(
@@ -178,18 +672,46 @@ impl InputManager {
         )
     }
 
-    /// Returns true if the key was released this frame.
+    /// Returns true if the held key repeated this tick, as it would when typing.
+    /// Isn't true on the tick the key was pressed, so check `key_pressed` as well for actions
+    /// that should happen on the press and each repeat, such as moving through a menu.
+    pub fn key_repeated(&self, key_code: KeyCode) -> bool {
+        self.repeated_keys.contains(&key_code)
+    }
+
+    /// Use software key repeat instead of the OS's, or go back to the OS's with `None`.
+    /// Software repeat uses the same delay and rate on every platform, and follows the simulation
+    /// clock. Repeats type the key's text again, so `text_input` repeats at the same rate.
+    ///
+    /// Input recordings save the setting in use when they start. While a recording is replayed,
+    /// its setting is used instead, and this one takes effect once the replay finishes.
+    pub fn set_key_repeat(&mut self, key_repeat: Option<KeyRepeat>) {
+        if let Some(replay) = self.replay.as_mut() {
+            replay.key_repeat = key_repeat;
+            return;
+        }
+
+        self.key_repeat = key_repeat;
+        self.key_holds.clear();
+    }
+
+    /// Get the software key repeat settings, if software repeat is used.
+    pub fn key_repeat(&self) -> Option<KeyRepeat> {
+        self.key_repeat
+    }
+
+    /// Returns true if the key was released this tick.
     pub fn key_released(&self, key_code: KeyCode) -> bool {
         self.input_map.get(&InputType::Key(key_code)) == Some(&InputState::Released)
     }
 
-    /// Returns true if the mouse button was pressed this frame.
+    /// Returns true if the mouse button was pressed this tick.
     pub fn mouse_pressed(&self, button: MouseButton) -> bool {
         self.input_map.get(&InputType::Mouse(button)) == Some(&InputState::Pressed)
     }
 
     /// Returns true if the mouse button is currently down.
-    /// Will return true for multiple frames if the mouse button is held down, including the frame
+    /// Will return true for multiple ticks if the mouse button is held down, including the tick
     /// it was pressed.
     pub fn mouse_down(&self, button: MouseButton) -> bool {
         matches!(
@@ -198,8 +720,223 @@ impl InputManager {
         )
     }
 
-    /// Returns true if the mouse button was released this frame.
+    /// Returns true if the mouse button was released this tick.
     pub fn mouse_released(&self, button: MouseButton) -> bool {
         self.input_map.get(&InputType::Mouse(button)) == Some(&InputState::Released)
     }
+
+    /// Set the named actions and axes queried with `action_pressed` and `axis`.
+    pub fn set_actions(&mut self, actions: ActionMap) {
+        self.actions = actions;
+    }
+
+    /// Get the named actions and axes.
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    /// Get the named actions and axes (mutable), e.g. to rebind an action.
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    /// Returns true if any binding for the action was pressed this tick.
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.actions
+            .any_binding(action, self, |trigger| trigger_pressed(self, trigger))
+    }
+
+    /// Returns true if any binding for the action is currently down.
+    pub fn action_down(&self, action: &str) -> bool {
+        self.actions
+            .any_binding(action, self, |trigger| trigger_down(self, trigger))
+    }
+
+    /// Returns true if a binding for the action was released this tick, and no other binding for
+    /// it is still down.
+    pub fn action_released(&self, action: &str) -> bool {
+        self.actions
+            .any_binding(action, self, |trigger| trigger_released(self, trigger))
+            && !self.action_down(action)
+    }
+
+    /// Get the value of an axis, from -1 to 1.
+    /// Each binding adds -1 or 1 while it is held, so opposite bindings cancel out.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.actions.axis_value(axis, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{DeviceId, TouchPhase};
+
+    use super::*;
+
+    fn window_event(event: WindowEvent) -> Event<()> {
+        // Dummy ids are only compared, never passed to the platform
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event,
+        }
+    }
+
+    fn device_id() -> DeviceId {
+        unsafe { DeviceId::dummy() }
+    }
+
+    /// An input manager that accepts the events made by `window_event`.
+    fn windowed() -> InputManager {
+        InputManager::new(Some(unsafe { WindowId::dummy() }))
+    }
+
+    #[test]
+    fn injected_keys_are_pressed_held_and_released_on_the_right_tick() {
+        let mut input = InputManager::detached();
+        input.inject(InputEvent::KeyDown(KeyCode::KeyA));
+        assert!(input.key_pressed(KeyCode::KeyA));
+        assert!(input.key_down(KeyCode::KeyA));
+
+        input.update();
+        assert_eq!(input.tick(), 1);
+        assert!(!input.key_pressed(KeyCode::KeyA));
+        assert!(input.key_down(KeyCode::KeyA));
+
+        // Pressing a key that is already down doesn't press it again
+        input.inject(InputEvent::KeyDown(KeyCode::KeyA));
+        assert!(!input.key_pressed(KeyCode::KeyA));
+        input.update();
+        assert!(input.key_down(KeyCode::KeyA));
+
+        input.inject(InputEvent::KeyUp(KeyCode::KeyA));
+        assert!(input.key_released(KeyCode::KeyA));
+        assert!(!input.key_down(KeyCode::KeyA));
+
+        input.update();
+        assert!(!input.key_released(KeyCode::KeyA));
+        assert!(!input.key_down(KeyCode::KeyA));
+    }
+
+    #[test]
+    fn injected_repeats_text_and_scrolling_last_one_tick() {
+        let mut input = InputManager::detached();
+        input.inject(InputEvent::KeyRepeat(KeyCode::KeyB));
+        input.inject(InputEvent::Char('h'));
+        input.inject(InputEvent::Char('i'));
+        input.inject(InputEvent::MouseWheel { x: 0.5, y: 1.0 });
+        input.inject(InputEvent::MouseWheel { x: 0.5, y: 2.0 });
+
+        assert!(input.key_repeated(KeyCode::KeyB));
+        assert_eq!(input.text_input(), "hi");
+        assert_eq!(input.scroll_delta(), (1.0, 3.0));
+
+        input.update();
+        assert!(!input.key_repeated(KeyCode::KeyB));
+        assert_eq!(input.text_input(), "");
+        assert_eq!(input.scroll_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn injected_mouse_input_matches_window_events() {
+        let mut injected = InputManager::detached();
+        let mut real = windowed();
+
+        let ticks = [
+            vec![(
+                InputEvent::MouseMove { x: 2.0, y: 3.0 },
+                WindowEvent::CursorMoved {
+                    device_id: device_id(),
+                    position: PhysicalPosition::new(2.0, 3.0),
+                },
+            )],
+            vec![
+                (
+                    InputEvent::MouseDown(MouseButton::Left),
+                    WindowEvent::MouseInput {
+                        device_id: device_id(),
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                    },
+                ),
+                (
+                    InputEvent::MouseMove { x: 5.0, y: 1.5 },
+                    WindowEvent::CursorMoved {
+                        device_id: device_id(),
+                        position: PhysicalPosition::new(5.0, 1.5),
+                    },
+                ),
+                (
+                    InputEvent::MouseWheel { x: 0.0, y: -1.0 },
+                    WindowEvent::MouseWheel {
+                        device_id: device_id(),
+                        delta: MouseScrollDelta::LineDelta(0.0, -1.0),
+                        phase: TouchPhase::Moved,
+                    },
+                ),
+            ],
+            vec![],
+            vec![(
+                InputEvent::MouseUp(MouseButton::Left),
+                WindowEvent::MouseInput {
+                    device_id: device_id(),
+                    state: ElementState::Released,
+                    button: MouseButton::Left,
+                },
+            )],
+            vec![(
+                InputEvent::MouseLeave,
+                WindowEvent::CursorLeft {
+                    device_id: device_id(),
+                },
+            )],
+        ];
+
+        for (tick, events) in ticks.into_iter().enumerate() {
+            for (input_event, event) in events {
+                injected.inject(input_event);
+                real.handle_event(&window_event(event));
+            }
+
+            for input in [&injected, &real] {
+                let state = (
+                    input.mouse_pressed(MouseButton::Left),
+                    input.mouse_down(MouseButton::Left),
+                    input.mouse_released(MouseButton::Left),
+                    input.mouse_position(),
+                    input.mouse_delta(),
+                    input.scroll_delta(),
+                );
+                let expected = match tick {
+                    0 => (false, false, false, Some((2, 3)), (0.0, 0.0), (0.0, 0.0)),
+                    1 => (true, true, false, Some((5, 1)), (3.0, -1.5), (0.0, -1.0)),
+                    2 => (false, true, false, Some((5, 1)), (0.0, 0.0), (0.0, 0.0)),
+                    3 => (false, false, true, Some((5, 1)), (0.0, 0.0), (0.0, 0.0)),
+                    _ => (false, false, false, None, (0.0, 0.0), (0.0, 0.0)),
+                };
+                assert_eq!(state, expected, "on tick {}", tick);
+            }
+
+            injected.update();
+            real.update();
+        }
+    }
+
+    #[test]
+    fn window_events_are_ignored_while_replaying() {
+        let mut recording = InputRecording::new();
+        recording.push(0, InputEvent::MouseDown(MouseButton::Right));
+        recording.set_ticks(2);
+
+        let mut input = windowed();
+        input.start_replay(recording);
+        input.begin_tick(Duration::ZERO);
+        input.handle_event(&window_event(WindowEvent::MouseInput {
+            device_id: device_id(),
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        }));
+
+        assert!(input.mouse_pressed(MouseButton::Right));
+        assert!(!input.mouse_down(MouseButton::Left));
+    }
 }
@@ -0,0 +1,109 @@
+/// A value that can be linearly interpolated.
+pub trait Lerp {
+    /// Interpolate between `self` at `t = 0` and `other` at `t = 1`.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t as f64
+    }
+}
+
+impl<A: Lerp, B: Lerp> Lerp for (A, B) {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+}
+
+impl<A: Lerp, B: Lerp, C: Lerp> Lerp for (A, B, C) {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        (
+            self.0.lerp(&other.0, t),
+            self.1.lerp(&other.1, t),
+            self.2.lerp(&other.2, t),
+        )
+    }
+}
+
+impl<T: Lerp, const N: usize> Lerp for [T; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+
+/// Double-buffered state, for drawing smoothly between fixed updates.
+/// Keeps the state from the previous update alongside the current one, so `draw` can interpolate
+/// between them using `RenderContext::alpha`.
+///
+/// ```ignore
+/// // In update
+/// self.position.advance();
+/// self.position.current_mut().0 += self.velocity;
+///
+/// // In draw
+/// let (x, y) = self.position.lerp(ctx.alpha());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Interpolated<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Clone> Interpolated<T> {
+    /// Create a new state, with no movement between the previous and current values.
+    pub fn new(value: T) -> Self {
+        Self {
+            previous: value.clone(),
+            current: value,
+        }
+    }
+
+    /// Copy the current value to the previous value.
+    /// Call this at the start of each update, before changing the current value.
+    pub fn advance(&mut self) {
+        self.previous = self.current.clone();
+    }
+
+    /// Set both values, so the state jumps without interpolating, e.g. when teleporting.
+    pub fn reset(&mut self, value: T) {
+        self.previous = value.clone();
+        self.current = value;
+    }
+}
+
+impl<T> Interpolated<T> {
+    /// Replace the current value, moving it to the previous value.
+    pub fn set(&mut self, value: T) {
+        self.previous = std::mem::replace(&mut self.current, value);
+    }
+
+    /// Get the value from the previous update
+    pub fn previous(&self) -> &T {
+        &self.previous
+    }
+
+    /// Get the value from the current update
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// Get the value from the current update (mutable)
+    pub fn current_mut(&mut self) -> &mut T {
+        &mut self.current
+    }
+}
+
+impl<T: Lerp> Interpolated<T> {
+    /// Interpolate between the previous and current values.
+    /// `alpha` is usually `RenderContext::alpha`.
+    pub fn lerp(&self, alpha: f32) -> T {
+        self.previous.lerp(&self.current, alpha)
+    }
+}
@@ -1,14 +1,52 @@
+mod action_map;
 mod app;
 mod app_settings;
+mod canvas;
+mod codec;
+mod color;
+mod crash;
+mod error;
+mod font;
+mod frame_loop;
+mod frame_stats;
+mod frame_transform;
+mod headless;
+mod image;
 mod input;
+mod interpolate;
+mod key_names;
+mod recording;
+mod rect;
 mod render_context;
+#[cfg(feature = "png")]
+mod snapshot;
+mod timers;
+#[cfg(feature = "ttf")]
+mod truetype;
 
 pub mod menu;
 
+pub use action_map::*;
 pub use app::*;
 pub use app_settings::*;
+pub use canvas::*;
+pub use codec::{ImageError, ImageErrorKind, ImageFormat};
+pub use color::*;
+pub use error::*;
+pub use font::*;
+pub use frame_stats::*;
+pub use headless::{HeadlessConfig, HeadlessPredicate, HeadlessRun};
+pub use image::*;
 pub use input::*;
+pub use interpolate::*;
+pub use recording::{InputRecording, RecordedEvent, INPUT_RECORDING_VERSION};
+pub use rect::*;
 pub use render_context::*;
+#[cfg(feature = "png")]
+pub use snapshot::*;
+pub use timers::*;
+#[cfg(feature = "ttf")]
+pub use truetype::*;
 
 pub use anyhow;
 pub use muda;
@@ -0,0 +1,483 @@
+//! Recording and replaying input, for reproducible bug reports and automated playthroughs.
+
+use std::{fmt, path::Path, time::Duration};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+use crate::{
+    key_names::{key_name, mouse_button_name, parse_key, parse_mouse_button},
+    AppSettings, InputEvent, InputManager, KeyRepeat, KeyboardModifiers,
+};
+
+/// The header on the first line of every recording.
+const HEADER: &str = "perovskite-input-recording";
+
+/// The version of the recording format written by this version of perovskite.
+pub const INPUT_RECORDING_VERSION: u32 = 1;
+
+/// An input event, and the fixed update tick it was applied before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedEvent {
+    /// The tick, counted from the start of the recording.
+    pub tick: u64,
+    pub event: InputEvent,
+}
+
+/// A recording of every input event that reached an `InputManager`, indexed by fixed update tick.
+/// Replaying a recording gives `App::update` the exact same input on every tick.
+///
+/// Recordings are plain text files, with one input event per line:
+///
+/// ```text
+/// perovskite-input-recording 1
+/// ticks 240
+/// software-repeat 0.500000000 0.033333333
+/// 0 mouse-move 320 240
+/// 12 key-down Space
+/// 12 char U+0020
+/// 15 key-up Space
+/// 30 modifiers left-shift left-control
+/// 31 mouse-down Left
+/// 40 wheel 0 -1
+/// ```
+///
+/// The first line names the format and its version. The `ticks` line is the number of fixed
+/// updates the recording covers. The optional `software-repeat` line is the delay and interval
+/// of software key repeat in seconds, if it was in use, see `InputManager::set_key_repeat`. Each
+/// event line starts with the tick it was applied before,
+/// counted from the start of the recording, and events are stored in the order they happened.
+///
+/// The events are:
+/// - `key-down <key>`, `key-up <key>` and `key-repeat <key>`, where keys are named after winit's
+///   `KeyCode` variants, e.g. `KeyA`, `Space` or `ArrowLeft`.
+/// - `char <character>`, for typed text. Whitespace, control characters and `#` are written as
+///   a Unicode code point, e.g. `U+0020` for a space.
+/// - `mouse-down <button>` and `mouse-up <button>`, where buttons are `Left`, `Right`, `Middle`,
+///   `Back`, `Forward` or a button number.
+/// - `mouse-move <x> <y>`, in physical pixels relative to the top left of the window.
+/// - `mouse-leave`, when the cursor leaves the window.
+/// - `wheel <x> <y>`, in lines.
+/// - `modifiers <modifier>...`, listing every modifier that is held, from `left-shift`,
+///   `right-shift`, `left-alt`, `right-alt`, `left-control`, `right-control`, `left-super` and
+///   `right-super`.
+///
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputRecording {
+    ticks: u64,
+    key_repeat: Option<KeyRepeat>,
+    events: Vec<RecordedEvent>,
+}
+
+impl InputRecording {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of ticks the recording covers.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Get the software key repeat setting the recording was made with.
+    pub fn key_repeat(&self) -> Option<KeyRepeat> {
+        self.key_repeat
+    }
+
+    /// Set the software key repeat setting to replay the recording with.
+    pub fn set_key_repeat(&mut self, key_repeat: Option<KeyRepeat>) {
+        self.key_repeat = key_repeat;
+    }
+
+    /// Get the recorded events, in the order they happened.
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Add an event to the end of the recording.
+    /// The recording is extended to cover the event's tick.
+    pub fn push(&mut self, tick: u64, event: InputEvent) {
+        self.ticks = self.ticks.max(tick + 1);
+        self.events.push(RecordedEvent { tick, event });
+    }
+
+    /// Set the number of ticks the recording covers.
+    /// The recording can't be made shorter than its last event.
+    pub fn set_ticks(&mut self, ticks: u64) {
+        let last = self.events.last().map_or(0, |event| event.tick + 1);
+        self.ticks = ticks.max(last);
+    }
+
+    /// Load a recording from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input recording {}", path.display()))?;
+
+        Self::parse(&text)
+            .with_context(|| format!("Failed to load input recording {}", path.display()))
+    }
+
+    /// Save the recording to a file.
+    /// Fails if the recording has a key without a name, as it couldn't be read back.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(RecordedEvent { tick, event }) = self
+            .events
+            .iter()
+            .find(|recorded| format_event(&recorded.event).is_none())
+        {
+            bail!(
+                "Failed to write input recording {}: the key in {:?} on tick {} has no name",
+                path.display(),
+                event,
+                tick
+            );
+        }
+
+        std::fs::write(path, self.to_string())
+            .with_context(|| format!("Failed to write input recording {}", path.display()))
+    }
+
+    /// Parse a recording from its text format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (_, header) = lines.next().ok_or_else(|| anyhow!("Recording is empty"))?;
+        let version = header
+            .strip_prefix(HEADER)
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| anyhow!("Not an input recording"))?;
+        ensure!(
+            version == INPUT_RECORDING_VERSION,
+            "Unsupported input recording version {}, expected {}",
+            version,
+            INPUT_RECORDING_VERSION
+        );
+
+        let mut recording = Self::new();
+        let mut ticks = None;
+
+        for (number, line) in lines {
+            let mut parts = line.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+
+            if first == "ticks" {
+                ticks = Some(parse_number(parts.next(), number)?);
+                continue;
+            }
+
+            if first == "software-repeat" {
+                recording.key_repeat = Some(KeyRepeat::new(
+                    parse_seconds(parts.next(), number)?,
+                    parse_seconds(parts.next(), number)?,
+                ));
+                continue;
+            }
+
+            let tick: u64 = first
+                .parse()
+                .map_err(|_| anyhow!("Line {}: invalid tick {:?}", number, first))?;
+            if recording.events.last().is_some_and(|last| last.tick > tick) {
+                bail!("Line {}: events are out of order", number);
+            }
+
+            let event = parse_event(parts.collect(), number)?;
+            recording.push(tick, event);
+        }
+
+        if let Some(ticks) = ticks {
+            recording.set_ticks(ticks);
+        }
+
+        Ok(recording)
+    }
+}
+
+impl fmt::Display for InputRecording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, INPUT_RECORDING_VERSION)?;
+        writeln!(f, "ticks {}", self.ticks)?;
+        if let Some(key_repeat) = self.key_repeat {
+            writeln!(
+                f,
+                "software-repeat {} {}",
+                format_seconds(key_repeat.delay),
+                format_seconds(key_repeat.interval)
+            )?;
+        }
+
+        for RecordedEvent { tick, event } in &self.events {
+            // Keys without a name can't be read back, so are left out, and `save` fails instead
+            if let Some(event) = format_event(event) {
+                writeln!(f, "{} {}", tick, event)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn format_event(event: &InputEvent) -> Option<String> {
+    let text = match *event {
+        InputEvent::KeyDown(key_code) => format!("key-down {}", key_name(key_code)?),
+        InputEvent::KeyUp(key_code) => format!("key-up {}", key_name(key_code)?),
+        InputEvent::KeyRepeat(key_code) => format!("key-repeat {}", key_name(key_code)?),
+        InputEvent::ModifiersChanged(modifiers) => {
+            let mut text = "modifiers".to_string();
+            for (name, held) in modifier_names(&modifiers) {
+                if held {
+                    text.push(' ');
+                    text.push_str(name);
+                }
+            }
+            text
+        }
+        InputEvent::Char(character) => format!("char {}", format_char(character)),
+        InputEvent::MouseMove { x, y } => format!("mouse-move {} {}", x, y),
+        InputEvent::MouseLeave => "mouse-leave".to_string(),
+        InputEvent::MouseDown(button) => format!("mouse-down {}", mouse_button_name(button)),
+        InputEvent::MouseUp(button) => format!("mouse-up {}", mouse_button_name(button)),
+        InputEvent::MouseWheel { x, y } => format!("wheel {} {}", x, y),
+    };
+
+    Some(text)
+}
+
+/// Format a character so it can't be mistaken for whitespace or a comment.
+fn format_char(character: char) -> String {
+    if character.is_whitespace() || character.is_control() || character == '#' {
+        format!("U+{:04X}", character as u32)
+    } else {
+        character.to_string()
+    }
+}
+
+fn parse_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(character), None) => Some(character),
+        _ => u32::from_str_radix(text.strip_prefix("U+")?, 16)
+            .ok()
+            .and_then(char::from_u32),
+    }
+}
+
+fn modifier_names(modifiers: &KeyboardModifiers) -> [(&'static str, bool); 8] {
+    [
+        ("left-shift", modifiers.left_shift),
+        ("right-shift", modifiers.right_shift),
+        ("left-alt", modifiers.left_alt),
+        ("right-alt", modifiers.right_alt),
+        ("left-control", modifiers.left_control),
+        ("right-control", modifiers.right_control),
+        ("left-super", modifiers.left_super),
+        ("right-super", modifiers.right_super),
+    ]
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&str>, line: usize) -> Result<T> {
+    let value = value.ok_or_else(|| anyhow!("Line {}: missing value", line))?;
+    value
+        .parse()
+        .map_err(|_| anyhow!("Line {}: invalid number {:?}", line, value))
+}
+
+/// Format a duration in seconds, to the nanosecond so it reads back exactly.
+fn format_seconds(duration: Duration) -> String {
+    format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos())
+}
+
+fn parse_seconds(value: Option<&str>, line: usize) -> Result<Duration> {
+    let value = value.ok_or_else(|| anyhow!("Line {}: missing value", line))?;
+    let invalid = || anyhow!("Line {}: invalid duration {:?}", line, value);
+
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 9 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let seconds = seconds.parse().map_err(|_| invalid())?;
+    let nanos = format!("{:0<9}", fraction).parse().map_err(|_| invalid())?;
+
+    Ok(Duration::new(seconds, nanos))
+}
+
+fn parse_event(parts: Vec<&str>, line: usize) -> Result<InputEvent> {
+    let key = |name: Option<&&str>| {
+        let name = name.copied().unwrap_or_default();
+        parse_key(name).ok_or_else(|| anyhow!("Line {}: unknown key {:?}", line, name))
+    };
+    let button = |name: Option<&&str>| {
+        let name = name.copied().unwrap_or_default();
+        parse_mouse_button(name)
+            .ok_or_else(|| anyhow!("Line {}: unknown mouse button {:?}", line, name))
+    };
+
+    let event = match parts.first().copied().unwrap_or_default() {
+        "key-down" => InputEvent::KeyDown(key(parts.get(1))?),
+        "key-up" => InputEvent::KeyUp(key(parts.get(1))?),
+        "key-repeat" => InputEvent::KeyRepeat(key(parts.get(1))?),
+        "mouse-down" => InputEvent::MouseDown(button(parts.get(1))?),
+        "mouse-up" => InputEvent::MouseUp(button(parts.get(1))?),
+        "mouse-move" => InputEvent::MouseMove {
+            x: parse_number(parts.get(1).copied(), line)?,
+            y: parse_number(parts.get(2).copied(), line)?,
+        },
+        "mouse-leave" => InputEvent::MouseLeave,
+        "char" => {
+            let text = parts.get(1).copied().unwrap_or_default();
+            let character = parse_char(text)
+                .ok_or_else(|| anyhow!("Line {}: invalid character {:?}", line, text))?;
+            InputEvent::Char(character)
+        }
+        "wheel" => InputEvent::MouseWheel {
+            x: parse_number(parts.get(1).copied(), line)?,
+            y: parse_number(parts.get(2).copied(), line)?,
+        },
+        "modifiers" => {
+            let mut modifiers = KeyboardModifiers::default();
+            for name in &parts[1..] {
+                let held = match *name {
+                    "left-shift" => &mut modifiers.left_shift,
+                    "right-shift" => &mut modifiers.right_shift,
+                    "left-alt" => &mut modifiers.left_alt,
+                    "right-alt" => &mut modifiers.right_alt,
+                    "left-control" => &mut modifiers.left_control,
+                    "right-control" => &mut modifiers.right_control,
+                    "left-super" => &mut modifiers.left_super,
+                    "right-super" => &mut modifiers.right_super,
+                    _ => bail!("Line {}: unknown modifier {:?}", line, name),
+                };
+                *held = true;
+            }
+            InputEvent::ModifiersChanged(modifiers)
+        }
+        other => bail!("Line {}: unknown event {:?}", line, other),
+    };
+
+    Ok(event)
+}
+
+/// Start recording or replaying input, as configured in the app settings.
+pub(crate) fn start_session(settings: &AppSettings, input: &mut InputManager) -> Result<()> {
+    if let Some(path) = settings.input_replay.as_ref() {
+        input.start_replay(InputRecording::load(path)?);
+    }
+
+    if settings.input_recording.is_some() {
+        input.start_recording();
+    }
+
+    Ok(())
+}
+
+/// Save the input recording, if the app settings asked for one.
+pub(crate) fn finish_session(settings: &AppSettings, input: &mut InputManager) -> Result<()> {
+    if let (Some(path), Some(recording)) =
+        (settings.input_recording.as_ref(), input.stop_recording())
+    {
+        recording.save(path)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{event::MouseButton, keyboard::KeyCode};
+
+    use super::*;
+
+    #[test]
+    fn round_trips_the_documented_format() {
+        let text = "\
+perovskite-input-recording 1
+ticks 240
+software-repeat 0.500000000 0.033333333
+0 mouse-move 320 240
+12 key-down Space
+12 char U+0020
+15 key-up Space
+30 modifiers left-shift left-control
+31 mouse-down Left
+40 wheel 0 -1
+";
+
+        let recording = InputRecording::parse(text).unwrap();
+        assert_eq!(recording.ticks(), 240);
+        assert_eq!(recording.key_repeat(), Some(KeyRepeat::default()));
+        assert_eq!(recording.events().len(), 7);
+        assert_eq!(
+            recording.events()[1],
+            RecordedEvent {
+                tick: 12,
+                event: InputEvent::KeyDown(KeyCode::Space)
+            }
+        );
+        assert_eq!(recording.to_string(), text);
+    }
+
+    #[test]
+    fn round_trips_every_event() {
+        let mut recording = InputRecording::new();
+        let modifiers = KeyboardModifiers {
+            right_alt: true,
+            left_super: true,
+            ..Default::default()
+        };
+        let events = [
+            InputEvent::KeyDown(KeyCode::KeyA),
+            InputEvent::KeyRepeat(KeyCode::KeyA),
+            InputEvent::KeyUp(KeyCode::KeyA),
+            InputEvent::Char('a'),
+            InputEvent::Char('#'),
+            InputEvent::Char('\n'),
+            InputEvent::Char('é'),
+            InputEvent::ModifiersChanged(modifiers),
+            InputEvent::ModifiersChanged(KeyboardModifiers::default()),
+            InputEvent::MouseMove { x: 1.5, y: -2.0 },
+            InputEvent::MouseLeave,
+            InputEvent::MouseDown(MouseButton::Other(9)),
+            InputEvent::MouseUp(MouseButton::Forward),
+            InputEvent::MouseWheel { x: 0.25, y: -3.0 },
+        ];
+        for (tick, event) in events.into_iter().enumerate() {
+            recording.push(tick as u64 / 2, event);
+        }
+        recording.set_ticks(100);
+        recording.set_key_repeat(Some(KeyRepeat::new(
+            Duration::from_secs(2),
+            Duration::from_nanos(1),
+        )));
+
+        let parsed = InputRecording::parse(&recording.to_string()).unwrap();
+        assert_eq!(parsed, recording);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        let header = "perovskite-input-recording 1\n";
+        for line in [
+            "0 key-down NotAKey",
+            "0 mouse-down Sideways",
+            "0 char ab",
+            "0 modifiers left-hyper",
+            "0 teleport",
+            "x key-down Space",
+            "software-repeat 0.5",
+            "software-repeat 0.5 0.0000000001",
+        ] {
+            let text = format!("{}{}", header, line);
+            assert!(InputRecording::parse(&text).is_err(), "{}", line);
+        }
+
+        let out_of_order = format!("{}2 mouse-leave\n1 mouse-leave", header);
+        assert!(InputRecording::parse(&out_of_order).is_err());
+        assert!(InputRecording::parse("perovskite-input-recording 2").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+/// An axis-aligned rectangle, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The x coordinate one past the right edge, saturating at `i32::MAX`.
+    pub const fn right(&self) -> i32 {
+        self.x.saturating_add(clamp_to_i32(self.width))
+    }
+
+    /// The y coordinate one past the bottom edge, saturating at `i32::MAX`.
+    pub const fn bottom(&self) -> i32 {
+        self.y.saturating_add(clamp_to_i32(self.height))
+    }
+
+    /// Returns true if the rectangle has no area.
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns true if the point is inside the rectangle.
+    pub const fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+    }
+
+    /// Returns the overlapping area of two rectangles, if there is one.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right > x && bottom > y {
+            Some(Rect::new(x, y, right.abs_diff(x), bottom.abs_diff(y)))
+        } else {
+            None
+        }
+    }
+}
+
+const fn clamp_to_i32(value: u32) -> i32 {
+    if value > i32::MAX as u32 {
+        i32::MAX
+    } else {
+        value as i32
+    }
+}
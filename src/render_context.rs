@@ -1,21 +1,60 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use pixels::{Pixels, SurfaceTexture};
-use winit::window::Window;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::Window,
+};
 
-use crate::InputManager;
+use crate::{frame_transform::FrameTransform, Canvas, FrameStats, InputManager, Rect, Timers};
+
+/// Where frames are drawn to.
+/// There is only ever one surface, so the size difference between variants doesn't matter.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Surface {
+    /// A window, presented with `pixels`.
+    Window { window: Window, pixels: Pixels },
+    /// An in-memory RGBA frame, for running without a window or GPU.
+    Headless { frame: Vec<u8> },
+}
+
+impl Surface {
+    fn frame_mut(&mut self) -> &mut [u8] {
+        match self {
+            Surface::Window { pixels, .. } => pixels.frame_mut(),
+            Surface::Headless { frame } => frame,
+        }
+    }
+}
 
 /// Update context
 #[derive(Debug)]
 pub struct RenderContext {
-    window: Window,
+    surface: Surface,
     target_frame_time: Duration,
     max_frame_time: Duration,
+    frame_width: u32,
+    frame_height: u32,
     should_exit: bool,
     pub(crate) delta_time: Duration,
+    pub(crate) alpha: f32,
+    pub(crate) lost_time: Duration,
+    pub(crate) dropped_ticks: u32,
+    pub(crate) total_dropped_ticks: u64,
+    time_scale: f32,
+    pub(crate) paused: bool,
+    pub(crate) step_requested: bool,
+    pub(crate) timers: Timers,
+    redraw_requested: bool,
+    redraw_deadline: Option<Instant>,
+    pub(crate) stats: FrameStats,
+    stats_overlay: bool,
+    /// The frame as the app drew it, while the stats overlay is presented over it.
+    overlay_backup: Vec<u8>,
+    ime_enabled: bool,
     pub input: InputManager,
-    pixels: Pixels,
 }
 
 impl RenderContext {
@@ -28,38 +67,275 @@ impl RenderContext {
         pixel_buffer_height: u32,
     ) -> Result<Self> {
         let id = window.id();
+        let window_size = window.inner_size();
 
         let pixels = {
-            let window_size = window.inner_size();
             let surface_texture =
                 SurfaceTexture::new(window_size.width, window_size.height, &window);
             Pixels::new(pixel_buffer_width, pixel_buffer_height, surface_texture)
         }
         .with_context(|| "Failed to create pixels context")?;
 
+        let mut input = InputManager::new(Some(id));
+        input.set_scale_factor(window.scale_factor());
+        input.set_frame_transform(FrameTransform::new(
+            pixel_buffer_width,
+            pixel_buffer_height,
+            window_size.width,
+            window_size.height,
+        ));
+
         Ok(Self {
-            window,
+            surface: Surface::Window { window, pixels },
             target_frame_time,
             max_frame_time,
+            frame_width: pixel_buffer_width,
+            frame_height: pixel_buffer_height,
             should_exit: false,
             delta_time: Duration::from_secs(0),
-            input: InputManager::new(id),
-            pixels,
+            alpha: 0.0,
+            lost_time: Duration::ZERO,
+            dropped_ticks: 0,
+            total_dropped_ticks: 0,
+            time_scale: 1.0,
+            paused: false,
+            step_requested: false,
+            timers: Timers::default(),
+            redraw_requested: false,
+            redraw_deadline: None,
+            stats: FrameStats::default(),
+            stats_overlay: false,
+            overlay_backup: Vec::new(),
+            ime_enabled: false,
+            input,
         })
     }
 
+    /// Create a new Context that draws to an in-memory frame, without a window
+    pub(crate) fn new_headless(
+        target_frame_time: Duration,
+        max_frame_time: Duration,
+        pixel_buffer_width: u32,
+        pixel_buffer_height: u32,
+    ) -> Self {
+        let frame = vec![0; pixel_buffer_width as usize * pixel_buffer_height as usize * 4];
+
+        // Without a window, positions are already in frame pixels
+        let mut input = InputManager::new(None);
+        input.set_frame_transform(FrameTransform::identity(
+            pixel_buffer_width,
+            pixel_buffer_height,
+        ));
+
+        Self {
+            surface: Surface::Headless { frame },
+            target_frame_time,
+            max_frame_time,
+            frame_width: pixel_buffer_width,
+            frame_height: pixel_buffer_height,
+            should_exit: false,
+            delta_time: Duration::from_secs(0),
+            alpha: 0.0,
+            lost_time: Duration::ZERO,
+            dropped_ticks: 0,
+            total_dropped_ticks: 0,
+            time_scale: 1.0,
+            paused: false,
+            step_requested: false,
+            timers: Timers::default(),
+            redraw_requested: false,
+            redraw_deadline: None,
+            stats: FrameStats::default(),
+            stats_overlay: false,
+            overlay_backup: Vec::new(),
+            ime_enabled: false,
+            input,
+        }
+    }
+
+    /// Returns true if the app is running without a window
+    pub fn is_headless(&self) -> bool {
+        matches!(self.surface, Surface::Headless { .. })
+    }
+
     /// Get the window
+    ///
+    /// # Panics
+    /// Panics when running headless, use `try_window` if the app can run without a window.
     pub fn window(&self) -> &Window {
-        &self.window
+        self.try_window()
+            .expect("There is no window when running headless")
     }
 
     /// Get the window (mutable)
+    ///
+    /// # Panics
+    /// Panics when running headless, use `try_window_mut` if the app can run without a window.
     pub fn window_mut(&mut self) -> &mut Window {
-        &mut self.window
+        self.try_window_mut()
+            .expect("There is no window when running headless")
     }
 
+    /// Get the `pixels` context used to present frames.
+    ///
+    /// # Panics
+    /// Panics when running headless, use `try_pixels_mut` if the app can run without a window.
     pub fn pixels_mut(&mut self) -> &mut Pixels {
-        &mut self.pixels
+        self.try_pixels_mut()
+            .expect("There is no pixels context when running headless")
+    }
+
+    /// Get the window.
+    /// Returns `None` when running headless.
+    pub fn try_window(&self) -> Option<&Window> {
+        match &self.surface {
+            Surface::Window { window, .. } => Some(window),
+            Surface::Headless { .. } => None,
+        }
+    }
+
+    /// Get the window (mutable).
+    /// Returns `None` when running headless.
+    pub fn try_window_mut(&mut self) -> Option<&mut Window> {
+        match &mut self.surface {
+            Surface::Window { window, .. } => Some(window),
+            Surface::Headless { .. } => None,
+        }
+    }
+
+    /// Get the `pixels` context used to present frames.
+    /// Returns `None` when running headless.
+    pub fn try_pixels_mut(&mut self) -> Option<&mut Pixels> {
+        match &mut self.surface {
+            Surface::Window { pixels, .. } => Some(pixels),
+            Surface::Headless { .. } => None,
+        }
+    }
+
+    /// Get the RGBA frame buffer
+    pub fn frame(&self) -> &[u8] {
+        match &self.surface {
+            Surface::Window { pixels, .. } => pixels.frame(),
+            Surface::Headless { frame } => frame,
+        }
+    }
+
+    /// Get the RGBA frame buffer (mutable)
+    pub fn frame_mut(&mut self) -> &mut [u8] {
+        self.surface.frame_mut()
+    }
+
+    /// Get a canvas for drawing to the frame buffer
+    pub fn canvas(&mut self) -> Canvas<'_> {
+        let (width, height) = (self.frame_width, self.frame_height);
+        Canvas::new(self.frame_mut(), width, height)
+    }
+
+    /// Present the frame buffer to the window.
+    /// Should be called at the end of `App::draw`. The stats overlay, if it is enabled, is drawn
+    /// over the presented frame only, so the frame buffer is left as the app drew it. When running
+    /// headless, there is nothing to present to, so this does nothing.
+    pub fn present(&mut self) -> Result<()> {
+        let Surface::Window { window, pixels } = &mut self.surface else {
+            self.stats.record_present(Duration::ZERO);
+            return Ok(());
+        };
+
+        if self.stats_overlay {
+            self.overlay_backup.clear();
+            self.overlay_backup.extend_from_slice(pixels.frame());
+            let mut canvas = Canvas::new(pixels.frame_mut(), self.frame_width, self.frame_height);
+            self.stats.draw_overlay(&mut canvas, self.target_frame_time);
+        }
+
+        let start = Instant::now();
+        window.pre_present_notify();
+        let result = pixels.render();
+        self.stats.record_present(start.elapsed());
+
+        if self.stats_overlay {
+            pixels.frame_mut().copy_from_slice(&self.overlay_backup);
+        }
+
+        result.with_context(|| "Failed to present frame")
+    }
+
+    /// Get timing statistics for recent frames
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Get timing statistics for recent frames (mutable)
+    pub fn frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.stats
+    }
+
+    /// Show or hide a graph of recent frame times, drawn over the frame when it is presented.
+    pub fn set_stats_overlay(&mut self, visible: bool) {
+        self.stats_overlay = visible;
+    }
+
+    /// Returns true if the frame time graph is shown
+    pub fn stats_overlay(&self) -> bool {
+        self.stats_overlay
+    }
+
+    /// Allow or disallow input methods, for typing text that needs composing, such as Chinese or
+    /// Japanese. Enable it while a text field has focus. IME is disabled by default.
+    /// Committed text arrives through `InputManager::text_input`.
+    pub fn enable_ime(&mut self, enabled: bool) {
+        self.ime_enabled = enabled;
+        if let Some(window) = self.try_window() {
+            window.set_ime_allowed(enabled);
+        }
+    }
+
+    /// Returns true if input methods are allowed
+    pub fn is_ime_enabled(&self) -> bool {
+        self.ime_enabled
+    }
+
+    /// Set the area of the frame being typed into, in frame buffer pixels, so the input method can
+    /// place its candidate window next to it. Usually the text cursor of the focused text field.
+    pub fn set_ime_cursor_area(&mut self, area: Rect) {
+        let (Some(window), Some(transform)) = (self.try_window(), self.input.frame_transform())
+        else {
+            return;
+        };
+
+        let (x, y) = transform.frame_to_window(area.x as f64, area.y as f64);
+        let (right, bottom) = transform.frame_to_window(area.right() as f64, area.bottom() as f64);
+        window.set_ime_cursor_area(
+            PhysicalPosition::new(x, y),
+            PhysicalSize::new(right - x, bottom - y),
+        );
+    }
+
+    /// Resize the surface frames are presented to, in physical pixels.
+    pub(crate) fn resize_surface(&mut self, width: u32, height: u32) -> Result<()> {
+        if let Surface::Window { pixels, .. } = &mut self.surface {
+            pixels.resize_surface(width, height).with_context(|| {
+                format!("Failed to resize pixels surface to {}x{}", width, height)
+            })?;
+            self.input.set_frame_transform(FrameTransform::new(
+                self.frame_width,
+                self.frame_height,
+                width,
+                height,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Get the width of the frame buffer, in pixels
+    pub fn frame_width(&self) -> u32 {
+        self.frame_width
+    }
+
+    /// Get the height of the frame buffer, in pixels
+    pub fn frame_height(&self) -> u32 {
+        self.frame_height
     }
 
     /// Get the target frame time
@@ -76,8 +352,126 @@ impl RenderContext {
         self.delta_time
     }
 
-    /// Set if the app should exit
+    /// Get how far between the last update and the next one the current frame is, from 0 to 1.
+    /// This is the time left over after running updates, divided by the target frame time.
+    /// Use it in `draw` to interpolate between the previous and current state, e.g. with
+    /// `Interpolated`.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Set how fast simulation time passes compared to real time.
+    /// For example, 0.5 runs the simulation in slow motion at half speed. With a fixed timestep,
+    /// this changes how often `update` is called rather than the delta time. The maximum frame
+    /// time limits real time, before it is scaled, so fast-forwarding isn't counted as lag.
+    /// Negative values are treated as 0.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = if time_scale.is_finite() {
+            time_scale.max(0.0)
+        } else {
+            1.0
+        };
+    }
+
+    /// Get how fast simulation time passes compared to real time
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Pause the simulation.
+    /// `update` stops being called, but `draw` keeps running. Pressed and released input, text
+    /// and scrolling still reset once per frame, but the input tick doesn't advance.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume the simulation after it was paused.
+    /// Time spent paused isn't caught up on.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.step_requested = false;
+    }
+
+    /// Returns true if the simulation is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pause the simulation if it is running, and run exactly one update on the next frame.
+    pub fn step_once(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    /// Get the timers, which fire `App::on_timer` after an amount of simulation time.
+    pub fn timers(&mut self) -> &mut Timers {
+        &mut self.timers
+    }
+
+    /// Get the simulation time thrown away in the last frame, because it took longer than the
+    /// maximum frame time or needed more than the maximum number of updates.
+    pub fn lost_time(&self) -> Duration {
+        self.lost_time
+    }
+
+    /// Get the number of whole ticks thrown away in the last frame.
+    pub fn dropped_ticks(&self) -> u32 {
+        self.dropped_ticks
+    }
+
+    /// Get the total number of ticks thrown away since the app started.
+    pub fn total_dropped_ticks(&self) -> u64 {
+        self.total_dropped_ticks
+    }
+
+    /// Request another frame as soon as possible.
+    /// Only needed with `RedrawMode::OnDemand`, e.g. while an animation is playing.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// Request a frame once the given time has passed.
+    /// Only needed with `RedrawMode::OnDemand`. If several are requested, the earliest is used.
+    pub fn request_redraw_after(&mut self, delay: Duration) {
+        self.request_redraw_at(Instant::now() + delay);
+    }
+
+    /// Request a frame at a specific time, keeping the earliest requested time.
+    pub(crate) fn request_redraw_at(&mut self, deadline: Instant) {
+        self.redraw_deadline = Some(
+            self.redraw_deadline
+                .map_or(deadline, |current| current.min(deadline)),
+        );
+    }
+
+    /// Returns true if the app asked to be redrawn as soon as possible
+    pub(crate) fn redraw_requested(&self) -> bool {
+        self.redraw_requested
+    }
+
+    /// Returns true if a redraw is due, clearing the request.
+    pub(crate) fn take_redraw_request(&mut self, now: Instant) -> bool {
+        let due = self.redraw_deadline.is_some_and(|deadline| deadline <= now);
+        if due {
+            self.redraw_deadline = None;
+        }
+
+        std::mem::take(&mut self.redraw_requested) || due
+    }
+
+    /// Get the time a redraw has been requested for, if any.
+    pub(crate) fn redraw_deadline(&self) -> Option<Instant> {
+        self.redraw_deadline
+    }
+
+    /// Request the app to exit.
+    /// The event loop will call `App::on_exit` and stop after the current `update` or `draw` returns.
     pub fn exit(&mut self) {
         self.should_exit = true;
     }
+
+    /// Returns true if the app has been requested to exit
+    pub fn should_exit(&self) -> bool {
+        self.should_exit
+    }
 }
@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::{App, Color, HeadlessConfig, Image};
+
+/// When this environment variable is set, snapshots are written instead of compared.
+/// Use it to create new snapshots, or accept changes to existing ones, e.g.
+/// `PEROVSKITE_BLESS=1 cargo test`.
+pub const BLESS_ENV_VAR: &str = "PEROVSKITE_BLESS";
+
+/// Run an app headless until it has called `App::update` a number of times, and return the frame
+/// drawn after the last of them.
+/// Fails if `updates` is zero, since no frame would be drawn.
+pub fn capture_frame<A: App>(app: A, updates: u64) -> Result<Image> {
+    ensure!(
+        updates > 0,
+        "Capturing a frame needs at least one update, so that a frame is drawn"
+    );
+
+    let run = A::run_headless(app, HeadlessConfig::updates(updates))?;
+    Ok(run.frame)
+}
+
+/// A golden image stored as a PNG, which frames are compared against.
+/// On a mismatch, the actual frame and a diff image are written next to the snapshot, as
+/// `<name>.actual.png` and `<name>.diff.png`. Mismatched pixels are red in the diff.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    path: PathBuf,
+    tolerance: u8,
+}
+
+impl Snapshot {
+    /// Create a snapshot stored at the given path.
+    /// By default, every channel must match exactly.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            tolerance: 0,
+        }
+    }
+
+    /// Set the maximum difference allowed in each channel of each pixel.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Get the path of the stored PNG.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compare an image with the snapshot.
+    /// If `PEROVSKITE_BLESS` is set, the image is written as the new snapshot instead.
+    pub fn check(&self, image: &Image) -> Result<()> {
+        self.check_or_bless(image, bless_requested())
+    }
+
+    fn check_or_bless(&self, image: &Image, bless: bool) -> Result<()> {
+        if bless {
+            return self.bless(image);
+        }
+
+        if !self.path.exists() {
+            bail!(
+                "Snapshot {} does not exist. Run with {}=1 to create it",
+                self.path.display(),
+                BLESS_ENV_VAR
+            );
+        }
+
+        let expected = Image::load(&self.path)?;
+        if (expected.width(), expected.height()) != (image.width(), image.height()) {
+            image.save_png(self.sibling("actual"))?;
+            bail!(
+                "Snapshot {} is {}x{}, but the frame is {}x{}",
+                self.path.display(),
+                expected.width(),
+                expected.height(),
+                image.width(),
+                image.height()
+            );
+        }
+
+        let Some(mismatch) = compare(&expected, image, self.tolerance) else {
+            return Ok(());
+        };
+
+        let diff_path = self.sibling("diff");
+        image.save_png(self.sibling("actual"))?;
+        mismatch.diff.save_png(&diff_path)?;
+
+        bail!(
+            "Snapshot {} does not match: {} of {} pixels differ by more than {} (largest difference {}). \
+             The diff was written to {}. Run with {}=1 to accept the new frame",
+            self.path.display(),
+            mismatch.pixels,
+            image.width() as u64 * image.height() as u64,
+            self.tolerance,
+            mismatch.max_difference,
+            diff_path.display(),
+            BLESS_ENV_VAR
+        );
+    }
+
+    /// Compare an image with the snapshot, panicking if it doesn't match.
+    /// Intended for use in tests.
+    #[track_caller]
+    pub fn assert(&self, image: &Image) {
+        if let Err(error) = self.check(image) {
+            panic!("{:#}", error);
+        }
+    }
+
+    /// Write the image as the new snapshot, creating any missing directories.
+    pub fn bless(&self, image: &Image) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create snapshot directory {}", parent.display())
+            })?;
+        }
+
+        image.save_png(&self.path)
+    }
+
+    /// The path of a file written next to the snapshot, e.g. `<name>.diff.png`.
+    fn sibling(&self, kind: &str) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!("{}.{}.png", stem, kind))
+    }
+}
+
+/// Compare an image with a stored PNG snapshot, panicking if it doesn't match.
+/// Shorthand for `Snapshot::new(path).with_tolerance(tolerance).assert(image)`.
+#[track_caller]
+pub fn assert_snapshot(path: impl Into<PathBuf>, image: &Image, tolerance: u8) {
+    Snapshot::new(path).with_tolerance(tolerance).assert(image);
+}
+
+fn bless_requested() -> bool {
+    std::env::var_os(BLESS_ENV_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+struct Mismatch {
+    pixels: u64,
+    max_difference: u8,
+    diff: Image,
+}
+
+/// Compare two images of the same size.
+/// Returns `None` if every channel is within the tolerance.
+fn compare(expected: &Image, actual: &Image, tolerance: u8) -> Option<Mismatch> {
+    let mut diff = Image::new(expected.width(), expected.height());
+    let mut pixels = 0;
+    let mut max_difference = 0;
+
+    let pairs = expected
+        .data()
+        .chunks_exact(4)
+        .zip(actual.data().chunks_exact(4));
+    for ((expected, actual), out) in pairs.zip(diff.data_mut().chunks_exact_mut(4)) {
+        let difference = expected
+            .iter()
+            .zip(actual)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+
+        let color = if difference > tolerance {
+            pixels += 1;
+            Color::RED
+        } else {
+            // Matching pixels are faded, so the mismatches stand out
+            let gray = ((expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 6) as u8;
+            Color::rgb(gray, gray, gray)
+        };
+        out.copy_from_slice(&color.to_array());
+    }
+
+    (pixels > 0).then_some(Mismatch {
+        pixels,
+        max_difference,
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AppSettings, RenderContext};
+
+    /// A directory for the snapshot files of a test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "perovskite-snapshot-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    struct FillApp {
+        color: Color,
+    }
+
+    impl App for FillApp {
+        fn init(&mut self) -> Result<AppSettings> {
+            Ok(AppSettings::builder().with_frame_size(4, 2).build())
+        }
+
+        fn update(&mut self, _ctx: &mut RenderContext) -> Result<()> {
+            Ok(())
+        }
+
+        fn draw(&mut self, ctx: &mut RenderContext) -> Result<()> {
+            ctx.canvas().clear(self.color);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn capture_frame_draws_the_app() {
+        let frame = capture_frame(FillApp { color: Color::RED }, 1).unwrap();
+
+        assert_eq!((frame.width(), frame.height()), (4, 2));
+        assert_eq!(frame, Image::filled(4, 2, Color::RED));
+        assert!(capture_frame(FillApp { color: Color::RED }, 0).is_err());
+    }
+
+    #[test]
+    fn compare_respects_the_tolerance() {
+        let expected = Image::filled(2, 1, Color::rgb(100, 100, 100));
+        let mut actual = expected.clone();
+        actual.canvas().set_pixel(1, 0, Color::rgb(100, 104, 100));
+
+        assert!(compare(&expected, &expected, 0).is_none());
+        assert!(compare(&expected, &actual, 4).is_none());
+
+        let mismatch = compare(&expected, &actual, 3).unwrap();
+        assert_eq!(mismatch.pixels, 1);
+        assert_eq!(mismatch.max_difference, 4);
+        assert_eq!(mismatch.diff.get_pixel(0, 0), Some(Color::rgb(50, 50, 50)));
+        assert_eq!(mismatch.diff.get_pixel(1, 0), Some(Color::RED));
+    }
+
+    #[test]
+    fn mismatches_write_the_actual_frame_and_a_diff() {
+        let dir = TempDir::new("mismatch");
+        let snapshot = Snapshot::new(dir.0.join("nested/frame.png")).with_tolerance(1);
+        let expected = Image::filled(2, 2, Color::BLUE);
+
+        assert!(snapshot.check_or_bless(&expected, false).is_err());
+        snapshot.check_or_bless(&expected, true).unwrap();
+        assert_eq!(Image::load(snapshot.path()).unwrap(), expected);
+        snapshot.check_or_bless(&expected, false).unwrap();
+
+        let mut actual = expected.clone();
+        actual.canvas().set_pixel(0, 1, Color::GREEN);
+        let error = snapshot.check_or_bless(&actual, false).unwrap_err();
+        assert!(error.to_string().contains("1 of 4 pixels"), "{}", error);
+
+        let written = Image::load(dir.0.join("nested/frame.actual.png")).unwrap();
+        assert_eq!(written, actual);
+        let diff = Image::load(dir.0.join("nested/frame.diff.png")).unwrap();
+        assert_eq!(diff.get_pixel(0, 1), Some(Color::RED));
+        assert_ne!(diff.get_pixel(0, 0), Some(Color::RED));
+
+        // The snapshot is untouched until it is blessed
+        assert_eq!(Image::load(snapshot.path()).unwrap(), expected);
+        snapshot.check_or_bless(&actual, true).unwrap();
+        snapshot.check_or_bless(&actual, false).unwrap();
+    }
+
+    #[test]
+    fn size_mismatches_write_the_actual_frame() {
+        let dir = TempDir::new("size");
+        let snapshot = Snapshot::new(dir.0.join("frame.png"));
+        snapshot.bless(&Image::new(2, 2)).unwrap();
+
+        let error = snapshot
+            .check_or_bless(&Image::new(3, 2), false)
+            .unwrap_err();
+        assert!(error.to_string().contains("is 2x2"), "{}", error);
+        assert_eq!(
+            Image::load(dir.0.join("frame.actual.png")).unwrap().width(),
+            3
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+/// An app-defined identifier for a timer.
+/// Several timers can share an ID, e.g. for a group of timers that should be cancelled together.
+pub type TimerId = u64;
+
+#[derive(Debug, Clone)]
+struct Timer {
+    id: TimerId,
+    deadline: Duration,
+    interval: Option<Duration>,
+}
+
+/// A scheduler driven by the fixed update clock.
+/// Timers only advance when `App::update` is called, so they slow down with the time scale and
+/// stop while the simulation is paused. Fired timers are delivered to `App::on_timer`, just
+/// before the update they fired in.
+#[derive(Debug, Default)]
+pub struct Timers {
+    now: Duration,
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    /// Fire a timer once, after the given amount of simulation time.
+    /// A delay too long to represent never fires.
+    pub fn after(&mut self, delay: Duration, id: TimerId) {
+        self.timers.push(Timer {
+            id,
+            deadline: self.now.saturating_add(delay),
+            interval: None,
+        });
+    }
+
+    /// Fire a timer repeatedly, every `interval` of simulation time.
+    /// A timer fires at most once per update. If more than one interval passes in a single
+    /// update, the missed fires are dropped, and the next fire is an interval after that update.
+    pub fn every(&mut self, interval: Duration, id: TimerId) {
+        self.timers.push(Timer {
+            id,
+            deadline: self.now.saturating_add(interval),
+            interval: Some(interval),
+        });
+    }
+
+    /// Cancel every timer with the given ID.
+    /// Returns true if any timers were cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let count = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != count
+    }
+
+    /// Cancel all timers.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    /// Returns true if a timer with the given ID is scheduled.
+    pub fn is_scheduled(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    /// Get the simulation time until the next timer with the given ID fires.
+    pub fn remaining(&self, id: TimerId) -> Option<Duration> {
+        self.timers
+            .iter()
+            .filter(|timer| timer.id == id)
+            .map(|timer| timer.deadline.saturating_sub(self.now))
+            .min()
+    }
+
+    /// Get the simulation time until any timer fires.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.timers
+            .iter()
+            .map(|timer| timer.deadline.saturating_sub(self.now))
+            .min()
+    }
+
+    /// Get the total simulation time the timers have been advanced by.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Advance the clock, and return the IDs of the timers that fired, in the order they fired.
+    pub(crate) fn advance(&mut self, delta_time: Duration) -> Vec<TimerId> {
+        self.now = self.now.saturating_add(delta_time);
+        let now = self.now;
+
+        // The first timer with the earliest deadline fires first, so timers added earlier win ties
+        let mut due: Vec<usize> = (0..self.timers.len())
+            .filter(|&index| self.timers[index].deadline <= now)
+            .collect();
+        due.sort_by_key(|&index| self.timers[index].deadline);
+
+        let fired = due.iter().map(|&index| self.timers[index].id).collect();
+
+        for &index in &due {
+            let timer = &mut self.timers[index];
+            if let Some(interval) = timer.interval {
+                // Fire at most once per update, so a short interval can't flood the update
+                let next = timer.deadline.saturating_add(interval);
+                timer.deadline = if next > now {
+                    next
+                } else {
+                    now.saturating_add(interval)
+                };
+            }
+        }
+
+        // One-shot timers that fired are done
+        self.timers
+            .retain(|timer| timer.interval.is_some() || timer.deadline > now);
+
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn fires_in_deadline_order() {
+        let mut timers = Timers::default();
+        timers.after(MS * 20, 1);
+        timers.after(MS * 10, 2);
+        timers.after(MS * 10, 3);
+
+        assert_eq!(timers.advance(MS * 5), Vec::<TimerId>::new());
+        assert_eq!(timers.advance(MS * 15), vec![2, 3, 1]);
+        assert!(!timers.is_scheduled(1));
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[test]
+    fn repeating_timers_fire_at_most_once_per_update() {
+        let mut timers = Timers::default();
+        timers.every(MS * 10, 1);
+
+        assert_eq!(timers.advance(MS * 10), vec![1]);
+        // Three intervals pass, but the timer only fires once, and restarts from now
+        assert_eq!(timers.advance(MS * 30), vec![1]);
+        assert_eq!(timers.remaining(1), Some(MS * 10));
+        assert_eq!(timers.advance(MS * 10), vec![1]);
+
+        timers.every(Duration::from_nanos(1), 2);
+        timers.every(Duration::ZERO, 3);
+        // The zero interval is due straight away, so fires first
+        assert_eq!(timers.advance(MS), vec![3, 2]);
+        assert_eq!(timers.advance(MS), vec![3, 2]);
+    }
+
+    #[test]
+    fn huge_delays_never_fire() {
+        let mut timers = Timers::default();
+        timers.advance(MS);
+        timers.after(Duration::MAX, 1);
+        timers.every(Duration::MAX, 2);
+
+        assert_eq!(
+            timers.advance(Duration::from_secs(1_000_000)),
+            Vec::<TimerId>::new()
+        );
+        assert!(timers.is_scheduled(1));
+        assert!(timers.is_scheduled(2));
+    }
+}
@@ -0,0 +1,397 @@
+use std::{collections::HashMap, path::Path};
+
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+use anyhow::{Context, Result};
+
+use crate::{
+    font::{align_offset, wrap_text},
+    TextOptions,
+};
+
+/// How glyph outlines are turned into pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GlyphRendering {
+    /// Edge pixels are partially covered, for smooth text.
+    #[default]
+    Antialiased,
+    /// Pixels are either fully covered or empty, for crisp pixel-art text.
+    HardEdged,
+}
+
+/// The size of a block of text, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextMetrics {
+    /// The width of the widest line.
+    pub width: f32,
+    /// The height of all lines, including line gaps.
+    pub height: f32,
+    /// The distance from the top of a line to the baseline.
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of a line. This is usually negative.
+    pub descent: f32,
+    /// The distance between the top of each line.
+    pub line_height: f32,
+    /// The number of lines, after wrapping.
+    pub lines: usize,
+}
+
+/// A glyph rasterized to a coverage mask.
+#[derive(Debug, Clone)]
+pub(crate) struct RasterGlyph {
+    /// The offset from the pen position on the baseline to the top left of the mask.
+    pub(crate) offset_x: i32,
+    pub(crate) offset_y: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Coverage of each pixel, from 0 to 255.
+    pub(crate) coverage: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    glyph: GlyphId,
+    size_bits: u32,
+    rendering: GlyphRendering,
+}
+
+/// The default maximum number of rasterized glyphs kept by a font.
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// A TrueType or OpenType font, rasterized on the CPU.
+/// Rasterized glyphs are cached per size, so drawing the same text repeatedly is cheap.
+/// The cache is cleared once it holds `cache_capacity` glyphs, so drawing at many different sizes
+/// doesn't grow it forever.
+#[derive(Debug)]
+pub struct TrueTypeFont {
+    font: FontVec,
+    rendering: GlyphRendering,
+    cache: HashMap<GlyphKey, RasterGlyph>,
+    cache_capacity: usize,
+}
+
+impl TrueTypeFont {
+    /// Load a font from a `.ttf` or `.otf` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read font {}", path.display()))?;
+
+        Self::from_bytes(data).with_context(|| format!("Failed to load font {}", path.display()))
+    }
+
+    /// Create a font from the contents of a `.ttf` or `.otf` file.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let font = FontVec::try_from_vec(data).with_context(|| "Invalid font data")?;
+
+        Ok(Self {
+            font,
+            rendering: GlyphRendering::default(),
+            cache: HashMap::new(),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+        })
+    }
+
+    /// Set how glyphs are rasterized
+    pub fn with_rendering(mut self, rendering: GlyphRendering) -> Self {
+        self.rendering = rendering;
+        self
+    }
+
+    /// Set how glyphs are rasterized
+    pub fn set_rendering(&mut self, rendering: GlyphRendering) {
+        self.rendering = rendering;
+    }
+
+    /// Get how glyphs are rasterized
+    pub fn rendering(&self) -> GlyphRendering {
+        self.rendering
+    }
+
+    /// Set the maximum number of rasterized glyphs to cache
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.set_cache_capacity(capacity);
+        self
+    }
+
+    /// Set the maximum number of rasterized glyphs to cache
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity;
+        if self.cache.len() > capacity {
+            self.cache.clear();
+        }
+    }
+
+    /// Get the maximum number of rasterized glyphs to cache
+    pub fn cache_capacity(&self) -> usize {
+        self.cache_capacity
+    }
+
+    /// Remove all cached glyphs, freeing their memory.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// The distance between the top of each line at a pixel size, including the line gap.
+    pub fn line_height(&self, size: f32) -> f32 {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        scaled.height() + scaled.line_gap()
+    }
+
+    /// Measure the width of a single line of text at a pixel size.
+    pub fn line_width(&self, line: &str, size: f32) -> f32 {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let mut width = 0.0;
+        let mut previous = None;
+
+        for character in line.chars() {
+            let glyph = scaled.glyph_id(character);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, glyph);
+            }
+            width += scaled.h_advance(glyph);
+            previous = Some(glyph);
+        }
+
+        width
+    }
+
+    /// Measure a block of text at a pixel size.
+    /// If `max_width` is set, lines are wrapped the same way as when drawing.
+    pub fn measure(&self, text: &str, size: f32, max_width: Option<u32>) -> TextMetrics {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let lines = self.wrap(text, size, max_width);
+        let width = lines
+            .iter()
+            .map(|line| self.line_width(line, size))
+            .fold(0.0, f32::max);
+        let line_height = self.line_height(size);
+
+        TextMetrics {
+            width,
+            height: lines.len() as f32 * line_height,
+            ascent: scaled.ascent(),
+            descent: scaled.descent(),
+            line_height,
+            lines: lines.len(),
+        }
+    }
+
+    fn wrap(&self, text: &str, size: f32, max_width: Option<u32>) -> Vec<String> {
+        wrap_text(text, max_width, |line| {
+            self.line_width(line, size).ceil() as u32
+        })
+    }
+
+    /// Position each glyph of a block of text, relative to its top left corner.
+    /// Returns each glyph with the pen position on its baseline.
+    pub(crate) fn layout(
+        &self,
+        text: &str,
+        size: f32,
+        options: &TextOptions,
+    ) -> Vec<(GlyphId, i32, i32)> {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let lines = self.wrap(text, size, options.max_width);
+        let widths: Vec<u32> = lines
+            .iter()
+            .map(|line| self.line_width(line, size).ceil() as u32)
+            .collect();
+        let block_width = options
+            .max_width
+            .unwrap_or_else(|| widths.iter().copied().max().unwrap_or(0));
+        let line_height = self.line_height(size);
+
+        let mut glyphs = Vec::new();
+        for (index, (line, width)) in lines.iter().zip(widths).enumerate() {
+            let mut x = align_offset(options.align, block_width, width) as f32;
+            let baseline = (index as f32 * line_height + scaled.ascent()).round() as i32;
+
+            let mut previous = None;
+            for character in line.chars() {
+                let glyph = scaled.glyph_id(character);
+                if let Some(previous) = previous {
+                    x += scaled.kern(previous, glyph);
+                }
+                previous = Some(glyph);
+
+                glyphs.push((glyph, x.round() as i32, baseline));
+                x += scaled.h_advance(glyph);
+            }
+        }
+
+        glyphs
+    }
+
+    /// Get a rasterized glyph from the cache, rasterizing it if needed.
+    pub(crate) fn rasterize(&mut self, glyph: GlyphId, size: f32) -> &RasterGlyph {
+        let key = GlyphKey {
+            glyph,
+            size_bits: size.to_bits(),
+            rendering: self.rendering,
+        };
+
+        if self.cache.len() >= self.cache_capacity.max(1) && !self.cache.contains_key(&key) {
+            self.cache.clear();
+        }
+
+        let font = &self.font;
+        let rendering = self.rendering;
+        self.cache.entry(key).or_insert_with(|| {
+            let positioned = glyph.with_scale_and_position(PxScale::from(size), point(0.0, 0.0));
+            let Some(outline) = font.outline_glyph(positioned) else {
+                return RasterGlyph {
+                    offset_x: 0,
+                    offset_y: 0,
+                    width: 0,
+                    height: 0,
+                    coverage: Vec::new(),
+                };
+            };
+
+            let bounds = outline.px_bounds();
+            let width = bounds.width() as u32;
+            let height = bounds.height() as u32;
+            let mut coverage = vec![0; width as usize * height as usize];
+
+            outline.draw(|x, y, amount| {
+                let value = match rendering {
+                    GlyphRendering::Antialiased => (amount.clamp(0.0, 1.0) * 255.0).round() as u8,
+                    GlyphRendering::HardEdged if amount >= 0.5 => 0xff,
+                    GlyphRendering::HardEdged => 0,
+                };
+                if let Some(pixel) = coverage.get_mut((y * width + x) as usize) {
+                    *pixel = value;
+                }
+            });
+
+            RasterGlyph {
+                offset_x: bounds.min.x as i32,
+                offset_y: bounds.min.y as i32,
+                width,
+                height,
+                coverage,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Color};
+
+    /// Build a font with 1000 units per em, where `A` is a 500 unit square sitting on the baseline,
+    /// 100 units in from a 700 unit advance.
+    fn square_font() -> Vec<u8> {
+        fn u16s(values: &[i32]) -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|&value| (value as u16).to_be_bytes())
+                .collect()
+        }
+
+        let mut head = u16s(&[1, 0, 1, 0, 0, 0, 0x5f0f, 0x3cf5, 0, 1000]);
+        head.extend([0; 16]);
+        head.extend(u16s(&[100, 0, 600, 500, 0, 8, 2, 0, 0]));
+
+        let mut hhea = u16s(&[1, 0, 800, -200, 0, 700]);
+        hhea.extend([0; 22]);
+        hhea.extend(u16s(&[2]));
+
+        let mut maxp = u16s(&[1, 0, 2]);
+        maxp.extend([0; 26]);
+
+        let hmtx = u16s(&[500, 0, 700, 100]);
+
+        // A square contour, with all points on the curve and each delta stored as an i16
+        let mut glyf = u16s(&[1, 100, 0, 600, 500, 3, 0]);
+        glyf.extend([1; 4]);
+        glyf.extend(u16s(&[100, 0, 500, 0, 0, 500, 0, -500]));
+        let loca = u16s(&[0, 0, glyf.len() as i32 / 2]);
+
+        // Windows Unicode, with one segment for `A` and the required final segment
+        let mut cmap = u16s(&[0, 1, 3, 1, 0, 12]);
+        cmap.extend(u16s(&[4, 32, 0, 4, 4, 1, 0]));
+        cmap.extend(u16s(&[65, 0xffff, 0, 65, 0xffff, 1 - 65, 1, 0, 0]));
+
+        let tables = [
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+
+        let mut font = u16s(&[1, 0, tables.len() as i32, 64, 2, 48]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, data) in &tables {
+            font.extend(*tag);
+            font.extend([0; 4]);
+            font.extend((offset as u32).to_be_bytes());
+            font.extend((data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in &tables {
+            font.extend(data);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+
+        font
+    }
+
+    #[test]
+    fn measures_text() {
+        let font = TrueTypeFont::from_bytes(square_font()).unwrap();
+
+        assert_eq!(font.line_width("AA", 10.0), 14.0);
+
+        let metrics = font.measure("AAA\nA", 10.0, None);
+        assert_eq!(metrics.width, 21.0);
+        assert_eq!(metrics.height, 20.0);
+        assert_eq!(metrics.ascent, 8.0);
+        assert_eq!(metrics.descent, -2.0);
+        assert_eq!(metrics.lines, 2);
+    }
+
+    #[test]
+    fn renders_glyphs() {
+        let mut font = TrueTypeFont::from_bytes(square_font()).unwrap();
+        let mut frame = vec![0; 10 * 10 * 4];
+        let mut canvas = Canvas::new(&mut frame, 10, 10);
+        canvas.draw_ttf_text(&mut font, 0, 0, "A", 10.0, &TextOptions::new(Color::WHITE));
+
+        // The square covers 5x5 pixels, starting 1 pixel in and ending on the baseline at 8
+        for y in 0..10 {
+            for x in 0..10 {
+                let expected = if (1..6).contains(&x) && (3..8).contains(&y) {
+                    Color::WHITE
+                } else {
+                    Color::TRANSPARENT
+                };
+                assert_eq!(canvas.get_pixel(x, y), Some(expected), "at ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn glyph_cache_is_bounded() {
+        let mut font = TrueTypeFont::from_bytes(square_font())
+            .unwrap()
+            .with_cache_capacity(2);
+        let glyph = font.font.glyph_id('A');
+
+        for size in 1..100 {
+            font.rasterize(glyph, size as f32);
+            assert!(font.cache.len() <= 2);
+        }
+
+        let raster = font.rasterize(glyph, 10.0);
+        assert_eq!((raster.width, raster.height), (5, 5));
+        assert_eq!((raster.offset_x, raster.offset_y), (1, -5));
+
+        font.clear_cache();
+        assert!(font.cache.is_empty());
+    }
+}